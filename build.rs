@@ -24,7 +24,8 @@ fn main() {
         filename.trim_end_matches(".rs").to_string()
     });
 
-    let names: Vec<_> = names.collect();
+    let mut names: Vec<_> = names.collect();
+    names.sort();
 
     let mut output = String::from("\
     // this file is generated by build.rs\n\
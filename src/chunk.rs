@@ -0,0 +1,36 @@
+use crate::types::*;
+use crate::constants::CHUNK_SIZE;
+
+// A tile in the dense tile grid of a chunk. Tiles have no components, so
+// anything that needs more state than this is a tile entity instead.
+#[derive(Clone, Copy)]
+pub struct Tile {
+    pub id: GameObjectTypeId,
+    pub state: u8, // the lowest two bits hold the texture rotation
+}
+
+pub struct Chunk {
+    tiles: Box<[Tile]>, // indexed by row * CHUNK_SIZE + col
+}
+
+impl Chunk {
+    pub fn new(tiles: Box<[Tile]>) -> Self {
+        assert_eq!(tiles.len(), CHUNK_SIZE * CHUNK_SIZE, "wrong amount of tiles");
+        Self { tiles }
+    }
+
+    pub fn get(&self, col: u8, row: u8) -> Tile {
+        self.tiles[row as usize * CHUNK_SIZE + col as usize]
+    }
+
+    #[allow(dead_code)]
+    pub fn set(&mut self, col: u8, row: u8, tile: Tile) {
+        self.tiles[row as usize * CHUNK_SIZE + col as usize] = tile;
+    }
+
+    // (col, row, tile) for every tile
+    pub fn iter(&self) -> impl Iterator<Item = (u8, u8, Tile)> + '_ {
+        self.tiles.iter().enumerate().map(|(i, tile)|
+            ((i % CHUNK_SIZE) as u8, (i / CHUNK_SIZE) as u8, *tile))
+    }
+}
@@ -10,4 +10,13 @@ pub use player::*;
 mod inventory;
 pub use inventory::*;
 
+mod velocity;
+pub use velocity::*;
+
+mod health;
+pub use health::*;
+
+mod uid;
+pub use uid::*;
+
 pub use crate::textures::TextureTransform;
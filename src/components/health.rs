@@ -0,0 +1,34 @@
+use hecs::Entity as EntityId;
+
+use serde::{Serialize, Deserialize};
+
+pub type HealthAmount = u16;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Health {
+    pub current: HealthAmount,
+    pub max: HealthAmount,
+}
+
+impl Health {
+    pub fn new(max: HealthAmount) -> Self {
+        Self {
+            current: max,
+            max,
+        }
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current == 0
+    }
+
+    pub fn heal_fully(&mut self) {
+        self.current = self.max;
+    }
+}
+
+#[derive(Clone)]
+pub struct Damage {
+    pub amount: HealthAmount,
+    pub source: Option<EntityId>, // whoever dealt the damage
+}
@@ -1,10 +1,89 @@
-type Amount = u8;
+use crate::prelude::*;
+use crate::utils::deserialize_interned;
+use crate::gameobjtype::GameObjectTypes;
 
+use serde::{Serialize, Deserialize};
+
+use std::collections::BTreeMap;
+
+pub type Amount = u8;
+
+// Extra state of a stack, like how worn out a tool is. Stacks only merge
+// when their metadata is the same.
+pub type Metadata = BTreeMap<String, MetaValue>;
+
+pub const DURABILITY: &str = "durability"; // uses left of a tool, unused if not set
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetaValue {
+    Int(i64),
+    Text(String),
+    Items(Vec<InventoryItem>),
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
 pub struct InventoryItem {
-    pub key: &'static str,
+    #[serde(deserialize_with = "deserialize_interned")]
+    pub key: Key,
     pub amount: Amount,
+    #[serde(default, skip_serializing_if = "Metadata::is_empty")]
+    pub metadata: Metadata,
 }
 
+impl InventoryItem {
+    pub fn new(key: Key, amount: Amount) -> Self {
+        Self {
+            key,
+            amount,
+            metadata: Metadata::new(),
+        }
+    }
+
+    // Whether the two can be in the same stack.
+    pub fn stacks_with(&self, other: &Self) -> bool {
+        self.key == other.key && self.metadata == other.metadata
+    }
+
+    pub fn durability(&self) -> Option<u16> {
+        match self.metadata.get(DURABILITY) {
+            Some(MetaValue::Int(left)) => Some((*left).clamp(0, u16::MAX as i64) as u16),
+            _ => None,
+        }
+    }
+
+    pub fn set_durability(&mut self, left: u16) {
+        self.metadata.insert(DURABILITY.to_string(), MetaValue::Int(left as i64));
+    }
+}
+
+// A slot an operation changed, and how much was in it before and after.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SlotChange {
+    pub slot: usize,
+    pub before: Amount,
+    pub after: Amount,
+}
+
+// An item lying in the world.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DroppedItem {
+    pub item: InventoryItem,
+    pub age: u32,   // updates since it was dropped
+    pub delay: u16, // updates until it can be picked up
+}
+
+impl DroppedItem {
+    pub fn new(item: InventoryItem, delay: u16) -> Self {
+        Self {
+            item,
+            age: 0,
+            delay,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Inventory {
     pub items: Box<[Option<InventoryItem>]>,
 }
@@ -22,25 +101,351 @@ impl Inventory {
         }
     }
 
-    pub fn try_receive(&mut self, key: &'static str, amount: Amount) {
-        if let Some(Some(item)) = self.items.iter_mut().find(|i|
-                    if let Some(i) = i {
-                        i.key == key
-                    } else {
-                        false
-                    }
-                ) {
+    // Whether any of an item would fit.
+    pub fn has_room_for(&self, types: &GameObjectTypes, item: &InventoryItem) -> bool {
+        let max = types.max_stack(item.key);
 
-            item.amount += amount;
-            // TODO check for threshold reached
+        self.items.iter().any(|stack| match stack {
+            Some(stack) => stack.stacks_with(item) && stack.amount < max,
+            None => true,
+        })
+    }
+
+    // Receives a new, unused item.
+    pub fn try_receive
+            (&mut self, types: &GameObjectTypes, key: &'static str, amount: Amount)
+            -> Amount {
 
-        } else if let Some(first_empty_index)
-                = self.items.iter().position(|i| i.is_none()) {
+        self.receive(types, InventoryItem::new(key, amount))
+    }
 
-            self.items[first_empty_index] = Some(InventoryItem {
-                key,
-                amount,
-            });
+    // Fills up the stacks the item stacks with that aren't full yet, then
+    // empty slots. Returns how much didn't fit.
+    pub fn receive(&mut self, types: &GameObjectTypes, item: InventoryItem) -> Amount {
+        let max = types.max_stack(item.key);
+        let mut amount = item.amount;
+
+        for stack in self.items.iter_mut().flatten() {
+            if stack.stacks_with(&item) && stack.amount < max {
+                let moved = amount.min(max - stack.amount);
+                stack.amount += moved;
+                amount -= moved;
+            }
+        }
+
+        for slot in self.items.iter_mut() {
+            if amount == 0 {
+                break
+            }
+
+            if slot.is_none() {
+                let moved = amount.min(max);
+                *slot = Some(InventoryItem {
+                    amount: moved,
+                    ..item.clone()
+                });
+                amount -= moved;
+            }
+        }
+
+        amount
+    }
+
+    // How much of an item there is over all slots. Only plain stacks count,
+    // so that a worn tool or a filled bag is never used up as an ingredient.
+    pub fn count(&self, key: &str) -> u32 {
+        self.items.iter()
+            .flatten()
+            .filter(|item| item.key == key && item.metadata.is_empty())
+            .map(|item| item.amount as u32)
+            .sum()
+    }
+
+    // Removes an amount of an item from any of the plain stacks, starting
+    // with the last slots, and returns the slots it was taken from. Nothing
+    // is removed if there isn't enough.
+    pub fn remove(&mut self, key: &str, amount: u32) -> Result<Vec<SlotChange>> {
+        let count = self.count(key);
+        if count < amount {
+            bail!("can't remove {} '{}', only {} left", amount, key, count)
+        }
+
+        let mut changes = Vec::new();
+        let mut left = amount;
+        for (i, slot) in self.items.iter_mut().enumerate().rev() {
+            if left == 0 {
+                break
+            }
+
+            if let Some(item) = slot && item.key == key && item.metadata.is_empty() {
+                let before = item.amount;
+                let removed = left.min(item.amount as u32);
+                item.amount -= removed as Amount;
+                left -= removed;
+
+                changes.push(SlotChange { slot: i, before, after: item.amount });
+
+                if item.amount == 0 {
+                    *slot = None;
+                }
+            }
         }
+
+        Ok(changes)
+    }
+
+    // Takes the whole stack out of a slot.
+    pub fn take_slot(&mut self, slot: usize) -> Result<Option<InventoryItem>> {
+        Ok(self.slot_mut(slot)?.take())
+    }
+
+    // Takes part of the stack out of a slot.
+    pub fn take_from_slot(&mut self, slot: usize, amount: Amount) -> Result<InventoryItem> {
+        let stack = self.slot_mut(slot)?;
+
+        let item = match stack {
+            Some(item) if amount > 0 && item.amount >= amount => item,
+            Some(item) => bail!("can't take {} from a stack of {}", amount, item.amount),
+            None => bail!("slot {} is empty", slot),
+        };
+
+        item.amount -= amount;
+        let taken = InventoryItem {
+            amount,
+            ..item.clone()
+        };
+
+        if item.amount == 0 {
+            *stack = None;
+        }
+
+        Ok(taken)
+    }
+
+    // Moves part of a stack into the first empty slot, and returns that slot.
+    pub fn split_slot(&mut self, slot: usize, amount: Amount) -> Result<usize> {
+        let empty = self.items.iter().position(|item| item.is_none())
+            .ok_or_else(|| anyhow!("no empty slot to split into"))?;
+
+        let item = self.take_from_slot(slot, amount)?;
+        self.items[empty] = Some(item);
+
+        Ok(empty)
+    }
+
+    // Returns what is in the two slots now.
+    pub fn swap_slots(&mut self, a: usize, b: usize) -> Result<[SlotChange; 2]> {
+        let amount_a = self.slot_mut(a)?.as_ref().map_or(0, |item| item.amount);
+        let amount_b = self.slot_mut(b)?.as_ref().map_or(0, |item| item.amount);
+        self.items.swap(a, b);
+
+        Ok([
+            SlotChange { slot: a, before: amount_a, after: amount_b },
+            SlotChange { slot: b, before: amount_b, after: amount_a },
+        ])
+    }
+
+    // Moves as much as fits of a slot into another inventory, and returns how
+    // much was moved. The rest stays in the slot.
+    pub fn move_to
+            (&mut self, types: &GameObjectTypes, slot: usize, other: &mut Inventory)
+            -> Result<Amount> {
+
+        let stack = self.slot_mut(slot)?;
+        let Some(item) = stack else { return Ok(0) };
+
+        let left = other.receive(types, item.clone());
+        let moved = item.amount - left;
+
+        item.amount = left;
+        if left == 0 {
+            *stack = None;
+        }
+
+        Ok(moved)
+    }
+
+    // Merges stacks of the same item and sorts them by key, with the empty
+    // slots at the end. Stacks over the limit, like from an older save, are
+    // split up, and whatever doesn't fit anymore is returned.
+    pub fn sort(&mut self, types: &GameObjectTypes) -> Vec<InventoryItem> {
+        let mut items: Vec<InventoryItem> = self.items.iter_mut()
+            .filter_map(|item| item.take())
+            .collect();
+        items.sort_by(|a, b| a.key.cmp(b.key).then_with(|| a.metadata.cmp(&b.metadata)));
+
+        let mut overflow = Vec::new();
+        for item in items {
+            let left = self.receive(types, item.clone());
+            if left > 0 {
+                overflow.push(InventoryItem {
+                    amount: left,
+                    ..item
+                });
+            }
+        }
+
+        overflow
+    }
+
+    fn slot_mut(&mut self, slot: usize) -> Result<&mut Option<InventoryItem>> {
+        let slots = self.items.len();
+        self.items.get_mut(slot)
+            .ok_or_else(|| anyhow!("slot {} out of range, there are {}", slot, slots))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inventory(items: &[Option<(Key, Amount)>]) -> Inventory {
+        let mut inventory = Inventory::new(items.len());
+        for (slot, item) in items.iter().enumerate() {
+            inventory.items[slot] = item.map(|(key, amount)| InventoryItem::new(key, amount));
+        }
+        inventory
+    }
+
+    fn amounts(inventory: &Inventory) -> Vec<Option<(Key, Amount)>> {
+        inventory.items.iter()
+            .map(|item| item.as_ref().map(|item| (item.key, item.amount)))
+            .collect()
+    }
+
+    #[test]
+    fn receive_respects_stack_limits() {
+        let types = GameObjectTypes::generate();
+        let mut inventory = inventory(&[Some(("chest_item", 10)), None, None]);
+
+        assert_eq!(inventory.try_receive(&types, "chest_item", 30), 0);
+        assert_eq!(amounts(&inventory),
+            [Some(("chest_item", 16)), Some(("chest_item", 16)), Some(("chest_item", 8))]);
+
+        assert_eq!(inventory.try_receive(&types, "chest_item", 10), 2);
+        assert!(!inventory.has_room_for(&types, &InventoryItem::new("chest_item", 1)));
+    }
+
+    #[test]
+    fn different_metadata_doesnt_stack() {
+        let types = GameObjectTypes::generate();
+        let mut inventory = Inventory::new(2);
+
+        let mut worn = InventoryItem::new("wood", 1);
+        worn.set_durability(3);
+        inventory.receive(&types, InventoryItem::new("wood", 1));
+        inventory.receive(&types, worn);
+
+        assert_eq!(amounts(&inventory), [Some(("wood", 1)), Some(("wood", 1))]);
+    }
+
+    #[test]
+    fn move_to_moves_what_fits() {
+        let types = GameObjectTypes::generate();
+        let mut from = inventory(&[Some(("chest_item", 12))]);
+        let mut to = inventory(&[Some(("chest_item", 10)), Some(("wood", 1))]);
+
+        assert_eq!(from.move_to(&types, 0, &mut to).unwrap(), 6);
+        assert_eq!(amounts(&from), [Some(("chest_item", 6))]);
+        assert_eq!(amounts(&to), [Some(("chest_item", 16)), Some(("wood", 1))]);
+
+        assert_eq!(from.move_to(&types, 0, &mut to).unwrap(), 0);
+        assert!(from.move_to(&types, 1, &mut to).is_err());
+    }
+
+    #[test]
+    fn split_slot() {
+        let mut inventory = inventory(&[Some(("wood", 9)), None]);
+
+        assert_eq!(inventory.split_slot(0, 4).unwrap(), 1);
+        assert_eq!(amounts(&inventory), [Some(("wood", 5)), Some(("wood", 4))]);
+
+        // no empty slot left
+        assert!(inventory.split_slot(0, 1).is_err());
+
+        let mut inventory = self::inventory(&[Some(("wood", 2)), None]);
+        assert!(inventory.split_slot(0, 3).is_err());
+        assert!(inventory.split_slot(1, 1).is_err());
+        assert_eq!(amounts(&inventory), [Some(("wood", 2)), None]);
+    }
+
+    #[test]
+    fn swap_slots() {
+        let mut inventory = inventory(&[Some(("wood", 3)), None, Some(("chest_item", 1))]);
+
+        let changes = inventory.swap_slots(0, 1).unwrap();
+        assert_eq!(changes, [
+            SlotChange { slot: 0, before: 3, after: 0 },
+            SlotChange { slot: 1, before: 0, after: 3 },
+        ]);
+        assert_eq!(amounts(&inventory), [None, Some(("wood", 3)), Some(("chest_item", 1))]);
+
+        assert!(inventory.swap_slots(0, 3).is_err());
+    }
+
+    #[test]
+    fn sort_merges_stacks() {
+        let types = GameObjectTypes::generate();
+        let mut inventory = inventory(&[
+            Some(("wood", 3)),
+            None,
+            Some(("chest_item", 1)),
+            Some(("wood", 62)),
+        ]);
+
+        assert!(inventory.sort(&types).is_empty());
+        assert_eq!(amounts(&inventory), [
+            Some(("chest_item", 1)),
+            Some(("wood", 64)),
+            Some(("wood", 1)),
+            None,
+        ]);
+    }
+
+    #[test]
+    fn sort_returns_what_doesnt_fit() {
+        let types = GameObjectTypes::generate();
+        let mut inventory = inventory(&[Some(("chest_item", 40)), Some(("wood", 1))]);
+
+        let overflow = inventory.sort(&types);
+        assert_eq!(amounts(&inventory), [Some(("chest_item", 16)), Some(("chest_item", 16))]);
+        assert_eq!(overflow, [InventoryItem::new("chest_item", 8), InventoryItem::new("wood", 1)]);
+    }
+
+    #[test]
+    fn remove() {
+        let mut inventory = inventory(&[Some(("wood", 5)), Some(("chest_item", 1)), Some(("wood", 2))]);
+
+        let changes = inventory.remove("wood", 4).unwrap();
+        assert_eq!(changes, [
+            SlotChange { slot: 2, before: 2, after: 0 },
+            SlotChange { slot: 0, before: 5, after: 3 },
+        ]);
+        assert_eq!(amounts(&inventory), [Some(("wood", 3)), Some(("chest_item", 1)), None]);
+    }
+
+    #[test]
+    fn remove_more_than_present() {
+        let mut inventory = inventory(&[Some(("wood", 5)), Some(("wood", 2))]);
+
+        assert!(inventory.remove("wood", 8).is_err());
+        assert!(inventory.remove("chest_item", 1).is_err());
+        assert_eq!(amounts(&inventory), [Some(("wood", 5)), Some(("wood", 2))]);
+        assert_eq!(inventory.count("wood"), 7);
+    }
+
+    #[test]
+    fn remove_skips_items_with_metadata() {
+        let mut inventory = inventory(&[
+            Some(("wooden_pickaxe", 1)), Some(("wooden_pickaxe", 1)), None]);
+        inventory.items[1].as_mut().unwrap().set_durability(3);
+
+        assert_eq!(inventory.count("wooden_pickaxe"), 1);
+        assert!(inventory.remove("wooden_pickaxe", 2).is_err());
+
+        let changes = inventory.remove("wooden_pickaxe", 1).unwrap();
+        assert_eq!(changes, [SlotChange { slot: 0, before: 1, after: 0 }]);
+        assert_eq!(inventory.items[1].as_ref().unwrap().durability(), Some(3));
+        assert_eq!(inventory.count("wooden_pickaxe"), 0);
     }
 }
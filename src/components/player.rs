@@ -1,9 +1,26 @@
+use crate::types::*;
 use crate::event::ActionState;
+use crate::components::Position;
 use hecs::Entity as EntityId;
 
+#[derive(Clone)]
+pub enum Selected {
+    Entity(EntityId),
+    Tile(Position), // a tile in the tile grid
+}
+
+// A block being broken.
+pub struct Mining {
+    pub target: EntityId,
+    pub progress: PosType, // from 0 to 1
+}
+
 pub struct Player {
-    pub selected: Option<EntityId>,
+    pub selected: Option<Selected>,
     pub action_state: ActionState,
+    pub mining: Option<Mining>,
+    pub attack_cooldown: u8, // updates until the next attack
+    pub selected_slot: usize, // inventory slot of the held item
 }
 
 impl Player {
@@ -11,6 +28,9 @@ impl Player {
         Self { 
             selected: None,
             action_state: ActionState::new(),
+            mining: None,
+            attack_cooldown: 0,
+            selected_slot: 0,
         }
     }
 }
@@ -1,9 +1,14 @@
 use crate::types::*;
 use crate::constants::CHUNK_SIZE;
 
-#[derive(Clone)]
+use serde::{Serialize, Deserialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Position {
+    // x and y are the offset within the chunk, so that positions far from
+    // the origin keep the same precision
     Free {
+        chunk: ChunkPos,
         x: PosType,
         y: PosType,
         top: bool,
@@ -17,7 +22,7 @@ pub enum Position {
     },
 }
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
 pub struct ChunkPos {
     pub x: ChunkPosType,
     pub y: ChunkPosType,
@@ -25,11 +30,19 @@ pub struct ChunkPos {
 
 impl Position {
     pub fn free(x: PosType, y: PosType) -> Self {
-        Self::Free {
+        Self::free_in(ChunkPos::new(0, 0), x, y)
+    }
+
+    // A free position at (x, y) relative to the corner of a chunk.
+    pub fn free_in(chunk: ChunkPos, x: PosType, y: PosType) -> Self {
+        let mut pos = Self::Free {
+            chunk,
             x,
             y,
             top: false,
-        }
+        };
+        pos.wrap();
+        pos
     }
 
     pub fn tile(chunk: ChunkPos, col: u8, row: u8) -> Self {
@@ -41,6 +54,18 @@ impl Position {
         }
     }
 
+    // The tile that the point (x, y), relative to `origin`, lies on.
+    pub fn tile_containing(origin: &ChunkPos, x: PosType, y: PosType) -> Self {
+        let size = CHUNK_SIZE as ChunkPosType;
+        let x = x.round() as ChunkPosType;
+        let y = y.round() as ChunkPosType;
+
+        Self::tile(
+            ChunkPos::new(origin.x + x.div_euclid(size), origin.y + y.div_euclid(size)),
+            x.rem_euclid(size) as u8,
+            y.rem_euclid(size) as u8)
+    }
+
     pub fn top(&mut self) -> &mut Self {
         match self {
             Self::Free { top, .. } => { *top = true; },
@@ -54,6 +79,7 @@ impl Position {
             Self::Free { x, .. } => { *x += amount; },
             Self::Tile { .. } => {},
         }
+        self.wrap();
     }
 
     pub fn move_y(&mut self, amount: PosType) { 
@@ -61,30 +87,66 @@ impl Position {
             Self::Free { y, .. } => { *y += amount; },
             Self::Tile { .. } => {},
         }
+        self.wrap();
     }
 
-    pub fn x(&self) -> PosType {
-        match &self {
-            Self::Free { x, .. } => *x,
-            Self::Tile { chunk, col, .. } =>
-                chunk.x as PosType * CHUNK_SIZE as PosType + *col as PosType 
+    // Moves the offset of a free position back into its chunk.
+    fn wrap(&mut self) {
+        fn wrap_axis(chunk: &mut ChunkPosType, offset: &mut PosType) {
+            let size = CHUNK_SIZE as PosType;
+            let shift = (*offset / size).floor();
+            *chunk += shift as ChunkPosType;
+            *offset -= shift * size;
+
+            // a tiny negative offset rounds up to exactly `size`
+            if *offset >= size {
+                *chunk += 1;
+                *offset -= size;
+            }
+        }
+
+        if let Self::Free { chunk, x, y, .. } = self {
+            wrap_axis(&mut chunk.x, x);
+            wrap_axis(&mut chunk.y, y);
         }
     }
 
-    pub fn y(&self) -> PosType {
+    // Coordinates relative to the corner of `origin`. These stay precise as
+    // long as `origin` is close by, no matter how far from the world origin.
+    pub fn x(&self, origin: &ChunkPos) -> PosType {
+        (self.chunk_ref().x - origin.x) as PosType * CHUNK_SIZE as PosType + self.local().0
+    }
+
+    pub fn y(&self, origin: &ChunkPos) -> PosType {
+        (self.chunk_ref().y - origin.y) as PosType * CHUNK_SIZE as PosType + self.local().1
+    }
+
+    // The offset within the chunk.
+    pub fn local(&self) -> (PosType, PosType) {
         match &self {
-            Self::Free { y, .. } => *y,
-            Self::Tile { chunk, row, .. } =>
-                chunk.y as PosType * CHUNK_SIZE as PosType + *row as PosType 
+            Self::Free { x, y, .. } => (*x, *y),
+            Self::Tile { col, row, .. } => (*col as PosType, *row as PosType),
         }
     }
 
+    // The world coordinates of the tile the position is on.
+    pub fn world_tile(&self) -> (i64, i64) {
+        let chunk = self.chunk_ref();
+        let (x, y) = self.local();
+        (
+            chunk.x as i64 * CHUNK_SIZE as i64 + x.floor() as i64,
+            chunk.y as i64 * CHUNK_SIZE as i64 + y.floor() as i64,
+        )
+    }
+
     pub fn chunk(&self) -> ChunkPos {
+        self.chunk_ref().clone()
+    }
+
+    fn chunk_ref(&self) -> &ChunkPos {
         match &self {
-            Self::Free { x, y, .. } => ChunkPos::new(
-                (x / CHUNK_SIZE as PosType).floor() as ChunkPosType,
-                (y / CHUNK_SIZE as PosType).floor() as ChunkPosType),
-            Self::Tile { chunk, .. } => chunk.clone()
+            Self::Free { chunk, .. } => chunk,
+            Self::Tile { chunk, .. } => chunk,
         }
     }
 
@@ -108,7 +170,6 @@ impl Position {
         }
     }
 
-    #[allow(dead_code)]
     pub fn is_tile(&self) -> bool {
         match &self {
             Self::Tile { .. } => true,
@@ -119,4 +180,9 @@ impl Position {
 
 impl ChunkPos {
     pub fn new(x: ChunkPosType, y: ChunkPosType) -> Self {
-        Self{x, y}}}
+        Self{x, y}}
+
+    pub fn distance(&self, other: &Self) -> ChunkPosType {
+        (self.x - other.x).abs().max((self.y - other.y).abs())
+    }
+}
@@ -0,0 +1,7 @@
+use serde::{Serialize, Deserialize};
+
+// Identifies an entity for as long as the world exists. ECS ids are handed out
+// again whenever entities are loaded, so anything that has to stay the same
+// across saves uses this instead.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct Uid(pub u64);
@@ -0,0 +1,9 @@
+use crate::types::*;
+
+// How far an entity moves every update. The movement is stopped by solid
+// objects, see `Game::move_entity`.
+#[derive(Clone, Default)]
+pub struct Velocity {
+    pub x: PosType,
+    pub y: PosType,
+}
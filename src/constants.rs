@@ -1,7 +1,14 @@
 use crate::types::*;
 
-pub const FPS: u64 = 60;
+pub const FPS: u64 = 60; // frames drawn per second, at most
+pub const TPS: u64 = 60; // ticks simulated per second
+pub const TICK_DURATION: PosType = 1.0 / TPS as PosType; // seconds
 pub const RESIZABLE: bool = true;
+pub const SAVE_DIR: &str = "saves/world";
 
 pub const CHUNK_SIZE: usize = 16;
 pub const RENDER_DISTANCE: usize = 3;
+pub const KEEP_ALIVE_DISTANCE: ChunkPosType = 3;
+pub const MAX_UNLOADED_CHUNKS: usize = 256; // kept in memory while there's no save
+pub const WORLDGEN_THREADS: usize = 2;
+pub const HOTBAR_SIZE: usize = 9; // the first inventory slots of the player
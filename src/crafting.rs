@@ -0,0 +1,126 @@
+// Recipes, defined by the json files in the recipes folder. The file name is
+// the key of the recipe.
+
+use crate::prelude::*;
+use crate::utils::{load_json_dir, deserialize_interned, deserialize_interned_option};
+use crate::components::{Inventory, InventoryItem};
+use crate::gameobjtype::GameObjectTypes;
+
+use serde::Deserialize;
+
+use std::path::Path;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Ingredient {
+    Item(#[serde(deserialize_with = "deserialize_interned")] Key),
+    Tag(String), // any mix of items with the tag
+}
+
+#[derive(Deserialize)]
+pub struct Input {
+    #[serde(flatten)]
+    pub ingredient: Ingredient,
+    pub amount: u32,
+}
+
+#[derive(Deserialize)]
+pub struct Recipe {
+    #[serde(skip)]
+    pub key: Key,
+    pub inputs: Vec<Input>,
+    pub outputs: Vec<InventoryItem>,
+    #[serde(default, deserialize_with = "deserialize_interned_option")]
+    pub station: Option<Key>, // block that has to be near the player
+}
+
+impl Recipe {
+    // Whether the inventory has all the inputs. The station isn't checked.
+    pub fn has_inputs(&self, types: &GameObjectTypes, inventory: &Inventory) -> bool {
+        self.take_inputs(types, &mut inventory.clone()).is_ok()
+    }
+
+    // Takes the inputs out of the inventory and puts the outputs in. Nothing
+    // changes if inputs are missing or the outputs don't fit.
+    pub fn craft(&self, types: &GameObjectTypes, inventory: &mut Inventory) -> Result<()> {
+        let mut crafted = inventory.clone();
+        self.take_inputs(types, &mut crafted)?;
+
+        for output in &self.outputs {
+            if crafted.receive(types, output.clone()) > 0 {
+                bail!("no room for the outputs of '{}'", self.key)
+            }
+        }
+
+        *inventory = crafted;
+
+        Ok(())
+    }
+
+    fn take_inputs(&self, types: &GameObjectTypes, inventory: &mut Inventory) -> Result<()> {
+        for input in &self.inputs {
+            match &input.ingredient {
+                Ingredient::Item(key) => {
+                    inventory.remove(key, input.amount)
+                        .with_context(|| format!("crafting '{}'", self.key))?;
+                },
+
+                Ingredient::Tag(tag) => {
+                    let mut keys: Vec<Key> = inventory.items.iter()
+                        .flatten()
+                        .map(|item| item.key)
+                        .filter(|key| types.has_tag(key, tag))
+                        .collect();
+                    keys.sort();
+                    keys.dedup();
+
+                    let mut left = input.amount;
+                    for key in keys {
+                        let taken = left.min(inventory.count(key));
+                        inventory.remove(key, taken)?;
+                        left -= taken;
+                    }
+
+                    if left > 0 {
+                        bail!("crafting '{}': missing {} items tagged '{}'", self.key, left, tag)
+                    }
+                },
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Loads every recipe in a folder, sorted by key so that they're always listed
+// in the same order.
+pub fn load_recipes(dir: &Path) -> Result<Vec<Recipe>> {
+    Ok(load_json_dir(dir, "recipe")?.into_iter()
+        .map(|(key, recipe)| Recipe { key, ..recipe })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outputs_keep_their_metadata() {
+        let types = GameObjectTypes::generate();
+
+        let mut output = InventoryItem::new("wooden_pickaxe", 1);
+        output.set_durability(7);
+        let recipe = Recipe {
+            key: "worn_pickaxe",
+            inputs: vec![Input { ingredient: Ingredient::Item("wood"), amount: 2 }],
+            outputs: vec![output.clone()],
+            station: None,
+        };
+
+        let mut inventory = Inventory::new(2);
+        inventory.try_receive(&types, "wood", 2);
+        recipe.craft(&types, &mut inventory).unwrap();
+
+        assert_eq!(inventory.items[..], [Some(output), None]);
+    }
+}
@@ -1,6 +1,8 @@
 use sdl2::keyboard::Scancode;
 use sdl2::mouse::MouseButton;
 
+use crate::constants::HOTBAR_SIZE;
+
 use std::collections::{HashMap, HashSet};
 
 pub type ActionIdType = u8;
@@ -128,7 +130,17 @@ impl ActionHandler {
     pub fn init(&mut self) {
         let mut actions = Vec::new();
 
-        for (button, action) in DEFAULT_BINDS {
+        let slot_binds = SLOT_BINDS.iter().map(|(scancode, key, label)| (
+            Button::Key(*scancode),
+            Action {
+                label,
+                key,
+                prolonged: false,
+                local: true,
+            },
+        ));
+
+        for (button, action) in DEFAULT_BINDS.into_iter().chain(slot_binds) {
             actions.push(action.clone());
             let id: ActionIdType = (actions.len() - 1)
                 .try_into().expect("couldn't add action. action id type is too small");
@@ -140,7 +152,7 @@ impl ActionHandler {
     }
 }
 
-const DEFAULT_BINDS: [(Button, Action); 7] = [
+const DEFAULT_BINDS: [(Button, Action); 11] = [
     (Button::Key(Scancode::W), Action {
         label: "Move up",
         key: "move_up",
@@ -169,6 +181,16 @@ const DEFAULT_BINDS: [(Button, Action); 7] = [
     (Button::Mouse(MouseButton::Right), Action {
         label: "Attack / Break",
         key: "attack",
+        prolonged: true,
+        local: false, }),
+    (Button::Mouse(MouseButton::Left), Action {
+        label: "Use / Place",
+        key: "use",
+        prolonged: false,
+        local: false, }),
+    (Button::Key(Scancode::Q), Action {
+        label: "Drop held stack",
+        key: "drop",
         prolonged: false,
         local: false, }),
     (Button::Key(Scancode::E), Action {
@@ -176,4 +198,27 @@ const DEFAULT_BINDS: [(Button, Action); 7] = [
         key: "toggle_inventory",
         prolonged: false,
         local: true, }),
+    (Button::Key(Scancode::R), Action {
+        label: "Sort inventory",
+        key: "sort_inventory",
+        prolonged: false,
+        local: true, }),
+    (Button::Key(Scancode::LCtrl), Action {
+        label: "Zoom with the mouse wheel",
+        key: "zoom",
+        prolonged: true,
+        local: true, }),
+];
+
+// (key, action, label) for selecting each hotbar slot.
+pub const SLOT_BINDS: [(Scancode, &str, &str); HOTBAR_SIZE] = [
+    (Scancode::Num1, "slot_1", "Select slot 1"),
+    (Scancode::Num2, "slot_2", "Select slot 2"),
+    (Scancode::Num3, "slot_3", "Select slot 3"),
+    (Scancode::Num4, "slot_4", "Select slot 4"),
+    (Scancode::Num5, "slot_5", "Select slot 5"),
+    (Scancode::Num6, "slot_6", "Select slot 6"),
+    (Scancode::Num7, "slot_7", "Select slot 7"),
+    (Scancode::Num8, "slot_8", "Select slot 8"),
+    (Scancode::Num9, "slot_9", "Select slot 9"),
 ];
@@ -1,16 +1,32 @@
 mod init;
 mod render;
 mod update;
+mod save;
+mod movement;
+mod health;
+mod mining;
+mod placing;
+mod items;
+mod containers;
+mod crafting;
+mod events;
 
 use crate::prelude::*;
 use crate::utils::Counter;
 use crate::gameobjtype::*;
+use crate::gameevent::{GameEvent, RecordedEvent};
 use crate::components::*;
-use crate::event::ActionHandler;
-use crate::constants::*;
+use crate::event::{ActionHandler, ActionUpdates};
 use crate::textures::Textures;
 use crate::ui::UIHandler;
 use crate::ui::tui;
+use crate::worldgen::{WorldGen, GeneratedChunk, ChunkGenerator, Biome, Structure};
+use crate::crafting::Recipe;
+use crate::random;
+use crate::save::{SavedEntity, SavedChunk, region};
+use crate::chunk::{Chunk, Tile};
+use crate::spatial::SpatialIndex;
+use crate::constants::{WORLDGEN_THREADS, KEEP_ALIVE_DISTANCE, MAX_UNLOADED_CHUNKS};
 
 use sdl2::rect::Rect;
 
@@ -22,14 +38,18 @@ use hecs::{
 };
 
 use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
 
 struct Loaded {
+    chunks: Vec<ChunkPos>, // chunks within render distance
     ids: Vec<EntityId>,
 }
 
 impl Loaded {
     fn new() -> Self {
         Self {
+            chunks: Vec::new(),
             ids: Vec::new(),
         }
     }
@@ -37,18 +57,43 @@ impl Loaded {
 
 gen_struct! { pub Game<'a> {
     ecs: ECSWorld = ECSWorld::new(),
+    spatial: SpatialIndex = SpatialIndex::new(),
     types: GameObjectTypes = GameObjectTypes::generate(),
+    // no threads until all definitions are loaded, see `start_worldgen`
+    worldgen: ChunkGenerator = ChunkGenerator::new(
+        WorldGen::new(random::int(0..=u64::MAX)), 0),
+    biomes: Arc<[Biome]> = Arc::new([]),
+    structures: Arc<[Structure]> = Arc::new([]),
+    recipes: Vec<Recipe> = Vec::new(),
     textures: Textures<'a> = HashMap::new(),
     font: tui::RenderedFont<'a> = tui::RenderedFont::empty(),
     loaded: Loaded = Loaded::new(),
     loaded_update_counter: Counter = Counter::new(60),
+    loaded_dirty: bool = false, // entities were spawned or despawned
     player: EntityId = EntityId::DANGLING,
-    chunks: Vec<ChunkPos> = Vec::new(),
+    next_uid: u64 = 0,
+    damage_queue: Vec<(EntityId, Damage)> = Vec::new(),
+    events: Vec<GameEvent> = Vec::new(), // of this tick
+    event_log: Vec<RecordedEvent> = Vec::new(), // not written to disk yet
+    record_dir: Option<PathBuf> = None, // where events are recorded, if at all
+    tick: u64 = 0,
+    actions: ActionUpdates = ActionUpdates::new(), // input since the last tick
+    scrolled: i32 = 0,
+    mouse: (i32, i32) = (0, 0), // on the screen, as of the last frame
+    ui_hovered: bool = false,
+    // where the entities that moved this tick were before, to draw them
+    // somewhere in between
+    previous: HashMap<EntityId, Position> = HashMap::new(),
+    interpolation: PosType = 1.0, // how far into the next tick the frame is
+    chunks: HashMap<ChunkPos, Chunk> = HashMap::new(),
+    unloaded: HashMap<ChunkPos, SavedChunk> = HashMap::new(),
+    save_dir: Option<PathBuf> = None,
     player_chunk: ChunkPos = ChunkPos::new(0, 0),
     tile_scale: u32 = 40,
     screen_size: (i32, i32) = (0, 0),
     action_handler: ActionHandler = ActionHandler::new(),
     ui_handler: UIHandler = UIHandler::new(),
+    open_container: Option<EntityId> = None, // shown next to the player inventory
     // last_mouse_pos: (i32, i32) = (0, 0),
 } pub new }
 
@@ -80,27 +125,64 @@ impl<'a> Game<'a> {
     }
 
     fn get_sdl_rect(&self, id: EntityId) -> Result<Rect> {
-        let pos = if let Ok(pos) = self.ecs.get::<&Position>(id) {
-            pos
+        let free = if let Ok(pos) = self.ecs.get::<&Position>(id) {
+            pos.is_free()
         } else {
             bail!("no position component");
         };
 
-        let player = self.ecs.get::<&Position>(self.player)?;
+        let camera = self.ecs.get::<&Position>(self.player)?.chunk();
+        let (x, y) = self.interpolated(id, &camera)?;
+        self.to_sdl_rect(&camera, x, y, free)
+    }
+
+    // Where an entity is drawn, between where it was at the start of the
+    // tick and where it is now, relative to `camera`.
+    fn interpolated(&self, id: EntityId, camera: &ChunkPos) -> Result<(PosType, PosType)> {
+        let pos = self.ecs.get::<&Position>(id)?;
+        let (x, y) = (pos.x(camera), pos.y(camera));
+
+        Ok(match self.previous.get(&id) {
+            Some(previous) => {
+                let (px, py) = (previous.x(camera), previous.y(camera));
+                (
+                    px + (x - px) * self.interpolation,
+                    py + (y - py) * self.interpolation,
+                )
+            },
+            None => (x, y),
+        })
+    }
 
-        let pos = if pos.is_free() {(
-            ((pos.x() as f32 - player.x()) * self.tile_scale as f32) as i32
-                + self.screen_size.0 as i32 / 2,
-            ((pos.y() as f32 - player.y()) * self.tile_scale as f32) as i32
-                + self.screen_size.1 as i32 / 2,
+    fn selected_sdl_rect(&self, selected: &Selected) -> Result<Rect> {
+        match selected {
+            Selected::Entity(id) => self.get_sdl_rect(*id),
+            Selected::Tile(pos) => self.pos_to_sdl_rect(pos),
+        }
+    }
+
+    fn pos_to_sdl_rect(&self, pos: &Position) -> Result<Rect> {
+        let camera = self.ecs.get::<&Position>(self.player)?.chunk();
+        self.to_sdl_rect(&camera, pos.x(&camera), pos.y(&camera), pos.is_free())
+    }
+
+    // Everything is drawn relative to the chunk the camera is in.
+    fn to_sdl_rect(&self, camera: &ChunkPos, x: PosType, y: PosType, free: bool) -> Result<Rect> {
+        let (player_x, player_y) = self.interpolated(self.player, camera)?;
+
+        let pos = if free {(
+            ((x - player_x) * self.tile_scale as f32) as i32
+                + self.screen_size.0 / 2,
+            ((y - player_y) * self.tile_scale as f32) as i32
+                + self.screen_size.1 / 2,
 
         )} else {(
-            self.screen_size.0 as i32 / 2
-                + (pos.x() * self.tile_scale as PosType) as i32
-                - (player.x() * self.tile_scale as f32) as i32,
-            self.screen_size.1 as i32 / 2
-                + (pos.y() * self.tile_scale as PosType) as i32
-                - (player.y() * self.tile_scale as f32) as i32,
+            self.screen_size.0 / 2
+                + (x * self.tile_scale as PosType) as i32
+                - (player_x * self.tile_scale as f32) as i32,
+            self.screen_size.1 / 2
+                + (y * self.tile_scale as PosType) as i32
+                - (player_y * self.tile_scale as f32) as i32,
         )};
 
         let rect = Rect::new(
@@ -113,6 +195,21 @@ impl<'a> Game<'a> {
         Ok(rect)
     }
 
+    #[allow(dead_code)]
+    pub fn biome_at(&self, pos: &Position) -> &Biome {
+        let (x, y) = pos.world_tile();
+        self.worldgen.worldgen().biome_at(x, y)
+    }
+
+    // The tile in the tile grid at a position, if its chunk is loaded.
+    fn get_tile(&self, pos: &Position) -> Option<Tile> {
+        if let Position::Tile { chunk, col, row, .. } = pos {
+            self.chunks.get(chunk).map(|chunk| chunk.get(*col, *row))
+        } else {
+            None
+        }
+    }
+
     fn spawn
             (&mut self, type_key: &'static str, components: impl DynamicBundle)
             -> Result<EntityId> {
@@ -122,22 +219,233 @@ impl<'a> Game<'a> {
         self.types.init_entity(&mut builder, type_key)?;
         let entity = builder.build();
         let id = self.ecs.spawn(entity);
+        self.assign_uid(id)?;
+        self.index_entity(id);
+        self.loaded_dirty = true;
         Ok(id)
     }
 
+    fn despawn(&mut self, id: EntityId) -> Result<()> {
+        self.spatial.remove(id);
+        self.ecs.despawn(id)?;
+        self.loaded_dirty = true;
+        Ok(())
+    }
+
+    // Every entity gets one when it's spawned or restored.
+    fn uid(&self, id: EntityId) -> Uid {
+        self.ecs.get::<&Uid>(id).map_or(Uid(u64::MAX), |uid| *uid)
+    }
+
+    // Gives an entity the next uid, unless it has one already, like entities
+    // restored from a save.
+    fn assign_uid(&mut self, id: EntityId) -> Result<()> {
+        match self.ecs.get::<&Uid>(id).map(|uid| uid.0) {
+            // chunks are saved on their own when unloaded, so they can hold
+            // uids the world file doesn't know were handed out
+            Ok(uid) => self.next_uid = self.next_uid.max(uid + 1),
+            Err(_) => {
+                self.ecs.insert_one(id, Uid(self.next_uid))?;
+                self.next_uid += 1;
+            },
+        }
+
+        Ok(())
+    }
+
+    fn index_entity(&mut self, id: EntityId) {
+        if let Ok(pos) = self.ecs.get::<&Position>(id) {
+            self.spatial.insert(id, &pos);
+        }
+    }
+
+    // Replaces the worldgen threads with ones generating the world of `seed`.
+    // Called once the biomes and structures are loaded, when creating or
+    // loading a world.
+    fn start_worldgen(&mut self, seed: u64) {
+        self.worldgen = ChunkGenerator::new(
+            WorldGen::new(seed)
+                .with_biomes(self.biomes.clone())
+                .with_structures(self.structures.clone()),
+            WORLDGEN_THREADS);
+    }
+
+    // Generates a chunk right away, instead of on the worldgen threads.
     fn generate_chunk
             (&mut self, pos: ChunkPos) -> Result<()> {
 
-        for col in 0..CHUNK_SIZE {
-            for row in 0..CHUNK_SIZE {
-                self.spawn("test_tile", (
-                    Position::tile(pos.clone(), col as u8, row as u8),
-                ))?;
+        let chunk = self.worldgen.worldgen().generate(&pos);
+        self.add_generated_chunk(chunk)
+    }
+
+    fn add_generated_chunk(&mut self, chunk: GeneratedChunk) -> Result<()> {
+        let tiles = chunk.tiles.iter().map(|(key, state)| Tile {
+            id: self.types.get_id(key),
+            state: *state,
+        }).collect();
+
+        for object in chunk.objects {
+            let id = self.spawn(object.key,
+                (Position::tile(chunk.pos.clone(), object.col, object.row),))?;
+
+            if !object.items.is_empty() {
+                if let Ok(mut inventory) = self.ecs.get::<&mut Inventory>(id) {
+                    for item in object.items {
+                        inventory.receive(&self.types, item);
+                    }
+                } else {
+                    let mut inventory = Inventory::new(object.items.len());
+                    inventory.items = object.items.into_iter().map(Some).collect();
+                    self.ecs.insert_one(id, inventory)?;
+                }
+            }
+        }
+
+        self.chunks.insert(chunk.pos, Chunk::new(tiles));
+
+        Ok(())
+    }
+
+    // Merges the chunks that the worldgen threads finished into the world.
+    // Returns whether there were any.
+    fn receive_chunks(&mut self) -> Result<bool> {
+        let mut received = false;
+
+        for chunk in self.worldgen.finished()? {
+            // the chunk might have been restored in the meantime, or the
+            // player might have left it behind
+            if self.chunks.contains_key(&chunk.pos)
+                    || chunk.pos.distance(&self.player_chunk) > KEEP_ALIVE_DISTANCE {
+                continue
             }
+
+            self.add_generated_chunk(chunk)?;
+            received = true;
         }
 
-        self.chunks.push(pos);
+        Ok(received)
+    }
+
+    fn load_chunk(&mut self, pos: ChunkPos) -> Result<()> {
+        let saved = self.unloaded.remove(&pos)
+            .or_else(|| self.read_saved_chunk(&pos));
 
+        if let Some(saved) = saved {
+            match saved.restore_tiles(&self.types) {
+                Ok(chunk) => {
+                    for entity in saved.entities {
+                        match entity.restore(&mut self.ecs, &self.types) {
+                            Ok(id) => {
+                                self.assign_uid(id)?;
+                                self.index_entity(id);
+                            },
+                            Err(err) => eprintln!(
+                                "error: restoring entity in chunk {:?}: {}", pos, err),
+                        }
+                    }
+                    self.chunks.insert(pos, chunk);
+                    return Ok(())
+                },
+                Err(err) => eprintln!(
+                    "error: restoring chunk {:?}: {}, generating it again", pos, err),
+            }
+        }
+
+        self.worldgen.request(pos);
         Ok(())
     }
+
+    // Writes the chunks to the save, or keeps them in memory until the world
+    // is first saved.
+    fn unload_chunks(&mut self, positions: &[ChunkPos]) -> Result<()> {
+        let mut unloaded = Vec::with_capacity(positions.len());
+
+        for pos in positions {
+            let ids = self.spatial.in_chunk(pos).to_vec();
+
+            let mut entities = Vec::with_capacity(ids.len());
+            for id in ids {
+                if id == self.player { continue }
+                self.spatial.remove(id);
+                entities.push(SavedEntity::take(&mut self.ecs, &self.types, id)?);
+            }
+
+            if let Some(chunk) = self.chunks.remove(pos) {
+                unloaded.push((pos.clone(), SavedChunk::new(&chunk, &self.types, entities)));
+            }
+        }
+
+        if let Some(dir) = &self.save_dir {
+            match region::write_chunks(dir, unloaded.iter().map(|(pos, chunk)| (pos, chunk))) {
+                Ok(()) => return Ok(()),
+                Err(err) => eprintln!(
+                    "error: writing unloaded chunks: {}, keeping them in memory", err),
+            }
+        }
+
+        self.unloaded.extend(unloaded);
+        self.forget_unloaded();
+
+        Ok(())
+    }
+
+    // Drops the unloaded chunks furthest from the player once there are too
+    // many. They are generated again when the player comes back.
+    fn forget_unloaded(&mut self) {
+        let excess = self.unloaded.len().saturating_sub(MAX_UNLOADED_CHUNKS);
+        if excess == 0 { return }
+
+        let mut positions: Vec<ChunkPos> = self.unloaded.keys().cloned().collect();
+        positions.sort_by_key(|pos| std::cmp::Reverse(pos.distance(&self.player_chunk)));
+
+        eprintln!("warning: forgetting {} unloaded chunks, the world isn't saved yet", excess);
+        for pos in positions.into_iter().take(excess) {
+            self.unloaded.remove(&pos);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::CHUNK_SIZE;
+
+    fn add_chunk(game: &mut Game, pos: ChunkPos) {
+        let tile = Tile { id: game.types.get_id("grass"), state: 0 };
+        game.chunks.insert(pos, Chunk::new(vec![tile; CHUNK_SIZE * CHUNK_SIZE].into()));
+    }
+
+    #[test]
+    fn unloaded_chunks_are_written_to_the_save() {
+        let dir = std::env::temp_dir().join(format!("crate-unload-{}", std::process::id()));
+        let mut game = Game::new();
+        game.save_dir = Some(dir.clone());
+
+        let pos = ChunkPos::new(-5, 7);
+        add_chunk(&mut game, pos.clone());
+        game.unload_chunks(std::slice::from_ref(&pos)).unwrap();
+
+        assert!(game.unloaded.is_empty());
+        assert!(game.read_saved_chunk(&pos).is_some());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn unloaded_chunks_are_bounded_without_a_save() {
+        let mut game = Game::new();
+
+        let positions: Vec<ChunkPos> = (0..MAX_UNLOADED_CHUNKS as ChunkPosType + 10)
+            .map(|x| ChunkPos::new(x, 0))
+            .collect();
+        for pos in &positions {
+            add_chunk(&mut game, pos.clone());
+        }
+        game.unload_chunks(&positions).unwrap();
+
+        // the ones furthest from the player are dropped
+        assert_eq!(game.unloaded.len(), MAX_UNLOADED_CHUNKS);
+        assert!(game.unloaded.contains_key(&ChunkPos::new(0, 0)));
+        assert!(!game.unloaded.contains_key(positions.last().unwrap()));
+    }
 }
@@ -0,0 +1,95 @@
+use super::*;
+
+impl<'a> Game<'a> {
+    // Opens the container the player is pointing at, if there is one.
+    // Returns whether it did.
+    pub(super) fn try_open_container(&mut self) -> Result<bool> {
+        let target = match self.ecs.get::<&Player>(self.player)?.selected {
+            Some(Selected::Entity(id)) => id,
+            _ => return Ok(false),
+        };
+
+        let is_container = self.ecs.get::<&GameObjectTypeComponent>(target)
+            .is_ok_and(|type_id| self.types.from_id(type_id.id).has_tag("container"));
+
+        if !is_container || self.ecs.get::<&Inventory>(target).is_err() {
+            return Ok(false)
+        }
+
+        let pos = (*self.ecs.get::<&Position>(target)?).clone();
+        if !self.in_reach(&pos)? {
+            return Ok(false)
+        }
+
+        self.open_container = Some(target);
+        if let Some(ui) = self.ui_handler.get_mut("container") {
+            ui.visible = true;
+        }
+        if let Some(ui) = self.ui_handler.get_mut("inventory") {
+            ui.visible = false;
+        }
+
+        Ok(true)
+    }
+
+    pub(super) fn close_container(&mut self) {
+        self.open_container = None;
+        if let Some(ui) = self.ui_handler.get_mut("container") {
+            ui.visible = false;
+        }
+    }
+
+    // Closes the container once it's gone or out of reach.
+    pub(super) fn update_container(&mut self) -> Result<()> {
+        let Some(id) = self.open_container else { return Ok(()) };
+
+        let in_reach = match self.ecs.get::<&Position>(id) {
+            Ok(pos) => {
+                let pos = (*pos).clone();
+                self.in_reach(&pos)?
+            },
+            Err(_) => false,
+        };
+
+        if !in_reach {
+            self.close_container();
+        }
+
+        Ok(())
+    }
+
+    // Moves the stack that was clicked in one pane of the container ui into
+    // the other pane.
+    pub(super) fn click_container(&mut self, x: i32, y: i32) -> Result<()> {
+        let Some(container) = self.open_container else { return Ok(()) };
+        let Some(ui) = self.ui_handler.get("container") else { return Ok(()) };
+        let rect = ui.to_rect(self.screen_size);
+
+        if !rect.contains_point((x, y)) {
+            return Ok(())
+        }
+
+        // the first line of each pane is its title
+        let line = self.font.px_to_ch_y((y - rect.y()) as u32) as usize;
+        if line == 0 {
+            return Ok(())
+        }
+        let slot = line - 1;
+
+        let left = x - rect.x() < rect.width() as i32 / 2;
+        let (from, to) = if left {
+            (container, self.player)
+        } else {
+            (self.player, container)
+        };
+
+        let [from, to] = self.ecs.query_many_mut::<&mut Inventory, 2>([from, to]);
+        let (Ok(from), Ok(to)) = (from, to) else { return Ok(()) };
+
+        if slot < from.items.len() {
+            from.move_to(&self.types, slot, to)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,69 @@
+use super::*;
+
+const STATION_RANGE: PosType = 3.0; // how close to a station the player has to be
+
+impl<'a> Game<'a> {
+    // The indices of the recipes the player could craft right now.
+    pub(super) fn craftable(&self) -> Result<Vec<usize>> {
+        let inventory = self.ecs.get::<&Inventory>(self.player)?;
+
+        Ok(self.recipes.iter()
+            .enumerate()
+            .filter(|(_, recipe)| recipe.has_inputs(&self.types, &inventory))
+            .filter(|(_, recipe)| recipe.station.is_none_or(|station| self.near_station(station)))
+            .map(|(i, _)| i)
+            .collect())
+    }
+
+    pub(super) fn craft(&mut self, recipe: usize) -> Result<()> {
+        let recipe = &self.recipes[recipe];
+
+        if let Some(station) = recipe.station && !self.near_station(station) {
+            bail!("crafting '{}' needs a '{}' nearby", recipe.key, station)
+        }
+
+        let mut inventory = self.ecs.get::<&mut Inventory>(self.player)?;
+        recipe.craft(&self.types, &mut inventory)
+    }
+
+    fn near_station(&self, station: &'static str) -> bool {
+        let Ok(player) = self.ecs.get::<&Position>(self.player) else { return false };
+        let origin = player.chunk();
+        let (x, y) = (player.x(&origin), player.y(&origin));
+        let station = self.types.get_id(station);
+
+        self.spatial.in_rect(
+                &origin,
+                x - STATION_RANGE,
+                y - STATION_RANGE,
+                x + STATION_RANGE,
+                y + STATION_RANGE)
+            .into_iter()
+            .any(|id| self.ecs.get::<&GameObjectTypeComponent>(id)
+                .is_ok_and(|type_id| type_id.id == station))
+    }
+
+    // Crafts the recipe that was clicked in the crafting list, which is the
+    // right half of the inventory ui.
+    pub(super) fn click_crafting(&mut self, x: i32, y: i32) -> Result<()> {
+        let Some(ui) = self.ui_handler.get("inventory") else { return Ok(()) };
+        let rect = ui.to_rect(self.screen_size);
+
+        if !ui.visible || !rect.contains_point((x, y)) || x - rect.x() < rect.width() as i32 / 2 {
+            return Ok(())
+        }
+
+        // the first line is the title
+        let line = self.font.px_to_ch_y((y - rect.y()) as u32) as usize;
+        if line == 0 {
+            return Ok(())
+        }
+
+        if let Some(recipe) = self.craftable()?.get(line - 1) {
+            // not having room for the outputs isn't an error for the game
+            update::handle_err("crafting", self.craft(*recipe));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,93 @@
+use super::*;
+use super::update::handle_err;
+use crate::constants::TPS;
+use crate::save;
+
+use std::path::Path;
+
+const FLUSH_INTERVAL: u64 = 10 * TPS; // ticks between writes of recorded events
+
+impl<'a> Game<'a> {
+    // Records every event from now on, to the event log in `dir`.
+    pub fn record_events(&mut self, dir: &Path) {
+        self.record_dir = Some(dir.to_path_buf());
+    }
+
+    pub(super) fn emit(&mut self, event: GameEvent) {
+        // recorded right away, since the entities might not be around by the
+        // end of the tick
+        if self.record_dir.is_some() {
+            let event = event.map_ids(|id| self.uid(id));
+            self.event_log.push(RecordedEvent { tick: self.tick, event });
+        }
+
+        self.events.push(event);
+    }
+
+    // Writes the recorded events to the event log every now and then, so
+    // they don't pile up and a crash only loses the last few.
+    pub(super) fn update_event_log(&mut self) -> Result<()> {
+        if self.tick.is_multiple_of(FLUSH_INTERVAL) {
+            self.flush_event_log()?;
+        }
+
+        Ok(())
+    }
+
+    pub(super) fn flush_event_log(&mut self) -> Result<()> {
+        if let Some(dir) = &self.record_dir && !self.event_log.is_empty() {
+            save::append_events(dir, &self.event_log).context("writing event log")?;
+            self.event_log.clear();
+        }
+
+        Ok(())
+    }
+
+    // Hands the events of the tick to the loaded entities whose types listen
+    // for them. Events emitted while doing so are handled next tick.
+    pub(super) fn dispatch_events(&mut self) {
+        let events = std::mem::take(&mut self.events);
+        if events.is_empty() {
+            return
+        }
+
+        let listeners: Vec<(EntityId, Uid, EventFnType)> = self.loaded.ids.iter()
+            .filter_map(|id| {
+                let type_id = self.ecs.get::<&GameObjectTypeComponent>(*id).ok()?.id;
+                Some((*id, self.uid(*id), self.types.get_event_fn(type_id)?))
+            })
+            .collect();
+
+        let mut update_data = UpdateData::new(
+            self.tick,
+            self.worldgen.worldgen().seed(),
+            self.player,
+            &self.types,
+            &self.spatial,
+            &self.chunks);
+
+        let listened: Vec<EntityId> = listeners.iter().map(|(id, ..)| *id).collect();
+        for (id, uid, event_fn) in listeners {
+            update_data.for_entity(uid);
+
+            for event in &events {
+                // earlier events could have despawned it
+                if !self.ecs.contains(id) {
+                    break
+                }
+
+                handle_err(&format!("handling an event for entity {}", id.id()),
+                    event_fn(&mut self.ecs, id, event, &mut update_data));
+            }
+        }
+
+        let commands = update_data.commands;
+        for id in listened {
+            self.spatial.refresh(id, &self.ecs);
+        }
+
+        for command in commands {
+            handle_err("applying command", self.apply_command(command));
+        }
+    }
+}
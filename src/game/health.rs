@@ -0,0 +1,64 @@
+use super::*;
+
+impl<'a> Game<'a> {
+    // Queues damage, which is dealt once per update by `apply_damage`.
+    pub fn damage(&mut self, target: EntityId, damage: Damage) {
+        self.damage_queue.push((target, damage));
+    }
+
+    pub(super) fn apply_damage(&mut self) {
+        for (id, damage) in std::mem::take(&mut self.damage_queue) {
+            // the target could have died earlier in the queue
+            let died = if let Ok(mut health) = self.ecs.get::<&mut Health>(id) {
+                let alive = !health.is_dead();
+                health.current = health.current.saturating_sub(damage.amount);
+                alive && health.is_dead()
+            } else {
+                continue
+            };
+
+            self.emit(GameEvent::EntityDamaged {
+                target: id,
+                amount: damage.amount,
+                source: damage.source,
+            });
+
+            if died && let Err(err) = self.kill(id, &damage) {
+                eprintln!("error: killing entity {}: {}", id.id(), err);
+            }
+        }
+    }
+
+    fn kill(&mut self, id: EntityId, damage: &Damage) -> Result<()> {
+        let type_id = self.ecs.get::<&GameObjectTypeComponent>(id)?.id;
+
+        self.emit(GameEvent::EntityDied {
+            target: id,
+            key: self.types.from_id(type_id).key,
+        });
+
+        self.spill_inventory(id)?;
+
+        if let Some(on_death) = self.types.get_death_fn(type_id) {
+            on_death(&mut self.ecs, id, damage)
+                .with_context(|| format!("death of '{}'", self.types.from_id(type_id).key))?;
+            self.spatial.refresh(id, &self.ecs);
+        }
+
+        let still_dead = self.ecs.get::<&Health>(id)
+            .map(|health| health.is_dead())
+            .unwrap_or(false);
+
+        if still_dead {
+            if id == self.player {
+                bail!("the player died without respawning");
+            }
+
+            let pos = (*self.ecs.get::<&Position>(id)?).clone();
+            self.give_drops(type_id, damage.source, &pos)?;
+            self.despawn(id)?;
+        }
+
+        Ok(())
+    }
+}
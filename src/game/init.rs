@@ -3,27 +3,29 @@ use crate::prelude::*;
 use crate::components::*;
 use crate::ui::tui;
 use crate::textures::load_textures;
+use crate::worldgen::{load_biomes, load_structures};
+use crate::crafting::{load_recipes, Ingredient};
 
 use sdl2::video::WindowContext;
 use sdl2::ttf::Sdl2TtfContext;
 use sdl2::render::TextureCreator;
 
 use std::collections::HashMap;
+use std::path::Path;
 
 impl<'a> Game<'a> {
     pub fn init(&mut self) -> Result<()> {
+        self.start_worldgen(self.worldgen.worldgen().seed());
+
         self.player = self.spawn("player", ({
             let mut inventory = Inventory::new(10);
-            inventory.try_receive("test", 1);
-            inventory.try_receive("error", 5);
+            inventory.try_receive(&self.types, "test", 1);
+            inventory.try_receive(&self.types, "error", 5);
             inventory
         },))?;
 
         self.spawn("player", ())?;
-        let chunk = ChunkPos::new(0, 0);
-        self.generate_chunk(chunk.clone())?;
-        self.spawn("tree", (Position::tile(chunk.clone(), 1, 1),))?;
-        self.spawn("tree", (Position::tile(chunk.clone(), 2, 1),))?;
+        self.generate_chunk(ChunkPos::new(0, 0))?;
         self.spawn("test", ())?;
         self.update_loaded(true)?;
 
@@ -34,6 +36,84 @@ impl<'a> Game<'a> {
 
     pub fn init_ui(&mut self) {
         self.ui_handler.add("inventory");
+        self.ui_handler.add("container");
+    }
+
+    pub fn init_biomes(&mut self) -> Result<()> {
+        let biomes = load_biomes(Path::new("assets/default/biomes"))?;
+
+        if biomes.is_empty() {
+            bail!("no biomes defined");
+        }
+
+        // worldgen runs on other threads, so unknown keys are caught here
+        for biome in &biomes {
+            let keys = [biome.ground, biome.patches].into_iter()
+                .chain(biome.features.iter().map(|feature| feature.key));
+
+            for key in keys {
+                if !self.types.contains(key) {
+                    bail!("unknown type '{}' in biome '{}'", key, biome.key);
+                }
+            }
+        }
+
+        self.biomes = biomes.into();
+
+        Ok(())
+    }
+
+    pub fn init_structures(&mut self) -> Result<()> {
+        let structures = load_structures(Path::new("assets/default/structures"))?;
+
+        // worldgen runs on other threads, so unknown keys are caught here
+        for structure in &structures {
+            for piece in &structure.pieces {
+                let keys = piece.ground.iter()
+                    .chain(piece.object.iter())
+                    .chain(piece.items.iter().map(|item| &item.key));
+
+                for key in keys {
+                    if !self.types.contains(key) {
+                        bail!("unknown type '{}' in structure '{}'", key, structure.key);
+                    }
+                }
+            }
+
+            for biome in &structure.biomes {
+                if !self.biomes.iter().any(|b| b.key == biome) {
+                    bail!("unknown biome '{}' in structure '{}'", biome, structure.key);
+                }
+            }
+        }
+
+        self.structures = structures.into();
+
+        Ok(())
+    }
+
+    pub fn init_recipes(&mut self) -> Result<()> {
+        let recipes = load_recipes(Path::new("assets/default/recipes"))?;
+
+        for recipe in &recipes {
+            let keys = recipe.inputs.iter()
+                .filter_map(|input| match &input.ingredient {
+                    Ingredient::Item(key) => Some(key),
+                    Ingredient::Tag(_) => None,
+                })
+                .chain(recipe.outputs.iter().map(|item| &item.key))
+                .chain(recipe.station.iter());
+
+            for key in keys {
+                if !self.types.contains(key) {
+                    bail!("unknown type '{}' in recipe '{}'", key, recipe.key);
+                }
+            }
+        }
+
+        self.recipes = recipes;
+
+        Ok(())
     }
 
     pub fn init_textures
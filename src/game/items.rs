@@ -0,0 +1,221 @@
+use super::*;
+use crate::constants::{TPS, TICK_DURATION};
+
+const DROP_DELAY: u16 = 30;         // updates until spilled items can be picked up
+const PLAYER_DROP_DELAY: u16 = 120; // same for items the player dropped
+const DESPAWN_AGE: u32 = 5 * 60 * TPS as u32;
+const PICKUP_RADIUS: PosType = 0.5;
+const MAGNET_RADIUS: PosType = 2.0;
+const MAGNET_SPEED: PosType = 9.0; // tiles per second
+const MERGE_RADIUS: PosType = 0.75;
+
+impl<'a> Game<'a> {
+    // Ages, merges and despawns dropped items, and lets the player pick them up.
+    pub(super) fn update_items(&mut self) -> Result<()> {
+        let mut expired = Vec::new();
+        let mut items = Vec::new();
+
+        for (id, dropped) in self.ecs.query_mut::<&mut DroppedItem>() {
+            dropped.age += 1;
+            dropped.delay = dropped.delay.saturating_sub(1);
+
+            if dropped.age >= DESPAWN_AGE {
+                expired.push(id);
+            } else {
+                items.push(id);
+            }
+        }
+
+        for id in expired {
+            self.despawn(id)?;
+        }
+
+        for id in items {
+            // it could have been merged into another one already
+            if self.ecs.contains(id) {
+                self.merge_items(id)?;
+            }
+        }
+
+        self.pick_up_items()
+    }
+
+    // Takes identical stacks lying close by into this one.
+    fn merge_items(&mut self, id: EntityId) -> Result<()> {
+        let pos = (*self.ecs.get::<&Position>(id)?).clone();
+        let item = self.ecs.get::<&DroppedItem>(id)?.item.clone();
+        let origin = pos.chunk();
+        let (x, y) = (pos.x(&origin), pos.y(&origin));
+
+        let others = self.spatial.in_rect(
+            &origin,
+            x - MERGE_RADIUS,
+            y - MERGE_RADIUS,
+            x + MERGE_RADIUS,
+            y + MERGE_RADIUS);
+
+        for other in others {
+            if other == id { continue }
+
+            let (amount, age) = match self.ecs.get::<&DroppedItem>(other) {
+                Ok(dropped) if dropped.item.stacks_with(&item) => (dropped.item.amount, dropped.age),
+                _ => continue,
+            };
+
+            let moved = {
+                let mut dropped = self.ecs.get::<&mut DroppedItem>(id)?;
+                let max = self.types.max_stack(item.key).max(dropped.item.amount);
+                let moved = amount.min(max - dropped.item.amount);
+                dropped.item.amount += moved;
+                // the merged stack is as old as the newer one
+                dropped.age = dropped.age.min(age);
+                moved
+            };
+
+            if moved == amount {
+                self.despawn(other)?;
+            } else {
+                self.ecs.get::<&mut DroppedItem>(other)?.item.amount -= moved;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn pick_up_items(&mut self) -> Result<()> {
+        let player = (*self.ecs.get::<&Position>(self.player)?).clone();
+        let origin = player.chunk();
+        let (px, py) = (player.x(&origin), player.y(&origin));
+
+        let nearby = self.spatial.in_rect(
+            &origin,
+            px - MAGNET_RADIUS,
+            py - MAGNET_RADIUS,
+            px + MAGNET_RADIUS,
+            py + MAGNET_RADIUS);
+
+        for id in nearby {
+            let item = match self.ecs.get::<&DroppedItem>(id) {
+                Ok(dropped) if dropped.delay == 0 => dropped.item.clone(),
+                _ => continue,
+            };
+
+            if !self.ecs.get::<&Inventory>(self.player)?.has_room_for(&self.types, &item) {
+                continue
+            }
+
+            let (dx, dy) = {
+                let pos = self.ecs.get::<&Position>(id)?;
+                (px - pos.x(&origin), py - pos.y(&origin))
+            };
+            let distance = (dx * dx + dy * dy).sqrt();
+
+            if distance <= PICKUP_RADIUS {
+                let left = self.ecs.get::<&mut Inventory>(self.player)?
+                    .receive(&self.types, item.clone());
+
+                self.emit(GameEvent::ItemPickedUp {
+                    by: self.player,
+                    item: InventoryItem {
+                        amount: item.amount - left,
+                        ..item
+                    },
+                });
+
+                if left == 0 {
+                    self.despawn(id)?;
+                } else {
+                    self.ecs.get::<&mut DroppedItem>(id)?.item.amount = left;
+                }
+            } else if distance <= MAGNET_RADIUS {
+                let step = (MAGNET_SPEED * TICK_DURATION).min(distance) / distance;
+                self.move_entity(id, dx * step, dy * step)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(super) fn spill(&mut self, item: InventoryItem, pos: &Position) -> Result<()> {
+        self.drop_item(item, pos, DROP_DELAY)
+    }
+
+    fn drop_item(&mut self, item: InventoryItem, pos: &Position, delay: u16) -> Result<()> {
+        let (x, y) = pos.local();
+        self.spawn("item", (
+            Position::free_in(pos.chunk(), x, y),
+            DroppedItem::new(item, delay),
+        ))?;
+        Ok(())
+    }
+
+    // Drops the stack in the held slot.
+    pub(super) fn drop_held_stack(&mut self) -> Result<()> {
+        let slot = self.ecs.get::<&Player>(self.player)?.selected_slot;
+        let item = self.ecs.get::<&mut Inventory>(self.player)?.take_slot(slot)?;
+
+        if let Some(item) = item {
+            let pos = (*self.ecs.get::<&Position>(self.player)?).clone();
+            self.drop_item(item, &pos, PLAYER_DROP_DELAY)?;
+        }
+
+        Ok(())
+    }
+
+    // Drops everything in the inventory of an entity, when it dies.
+    pub(super) fn spill_inventory(&mut self, id: EntityId) -> Result<()> {
+        let items: Vec<InventoryItem> = match self.ecs.get::<&mut Inventory>(id) {
+            Ok(mut inventory) =>
+                inventory.items.iter_mut().filter_map(|item| item.take()).collect(),
+            Err(_) => return Ok(()),
+        };
+
+        let pos = (*self.ecs.get::<&Position>(id)?).clone();
+        for item in items {
+            self.spill(item, &pos)?;
+        }
+
+        Ok(())
+    }
+
+    // Sorts the inventory of the player, and spills what no longer fits.
+    pub(super) fn sort_inventory(&mut self) -> Result<()> {
+        let overflow = self.ecs.get::<&mut Inventory>(self.player)?.sort(&self.types);
+
+        let pos = (*self.ecs.get::<&Position>(self.player)?).clone();
+        for item in overflow {
+            self.spill(item, &pos)?;
+        }
+
+        Ok(())
+    }
+
+    // Swaps the slot that was clicked in the inventory ui with the held one,
+    // or splits half of it off if `split` is set.
+    pub(super) fn click_inventory(&mut self, x: i32, y: i32, split: bool) -> Result<()> {
+        let Some(ui) = self.ui_handler.get("inventory") else { return Ok(()) };
+        let rect = ui.to_rect(self.screen_size);
+
+        if !ui.visible || !rect.contains_point((x, y)) || x - rect.x() >= rect.width() as i32 / 2 {
+            return Ok(())
+        }
+
+        // one slot per line
+        let slot = self.font.px_to_ch_y((y - rect.y()) as u32) as usize;
+        let selected = self.ecs.get::<&Player>(self.player)?.selected_slot;
+        let mut inventory = self.ecs.get::<&mut Inventory>(self.player)?;
+
+        let Some(item) = inventory.items.get(slot) else { return Ok(()) };
+        let amount = item.as_ref().map_or(0, |item| item.amount);
+
+        if split {
+            if amount > 1 && inventory.items.iter().any(|item| item.is_none()) {
+                inventory.split_slot(slot, amount / 2)?;
+            }
+        } else if slot != selected {
+            inventory.swap_slots(slot, selected)?;
+        }
+
+        Ok(())
+    }
+}
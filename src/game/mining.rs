@@ -0,0 +1,173 @@
+use super::*;
+use crate::constants::TPS;
+
+const ATTACK_COOLDOWN: u8 = 20; // updates between attacks
+const TIER_SPEED: PosType = 2.0; // how much faster each tier of a tool mines
+
+impl<'a> Game<'a> {
+    // Holding attack on a block breaks it over time, anything else with
+    // health gets hit every now and then.
+    pub(super) fn update_attack(&mut self, target: Option<Selected>) -> Result<()> {
+        {
+            let mut player = self.ecs.get::<&mut Player>(self.player)?;
+            player.attack_cooldown = player.attack_cooldown.saturating_sub(1);
+        }
+
+        // the target might have been despawned since it was selected, and
+        // has to be as close as a block would have to be to place it
+        let (target, type_id) = if let Some(Selected::Entity(id)) = target
+                && let Ok(type_id) = self.ecs.get::<&GameObjectTypeComponent>(id).map(|c| c.id)
+                && let Ok(pos) = self.ecs.get::<&Position>(id).map(|pos| (*pos).clone())
+                && self.in_reach(&pos)? {
+            (id, type_id)
+        } else {
+            self.ecs.get::<&mut Player>(self.player)?.mining = None;
+            return Ok(())
+        };
+
+        if let Some(hardness) = self.types.from_id(type_id).hardness {
+            let step = self.mining_speed(type_id)? / (hardness * TPS as PosType);
+
+            let done = {
+                let mut player = self.ecs.get::<&mut Player>(self.player)?;
+                let mining = match &mut player.mining {
+                    Some(mining) if mining.target == target => mining,
+                    mining => mining.insert(Mining { target, progress: 0.0 }),
+                };
+                mining.progress += step;
+                mining.progress >= 1.0
+            };
+
+            if done {
+                self.ecs.get::<&mut Player>(self.player)?.mining = None;
+                self.break_block(target)?;
+                self.wear_held_tool()?;
+            }
+        } else {
+            let attack = {
+                let mut player = self.ecs.get::<&mut Player>(self.player)?;
+                player.mining = None;
+
+                let attack = player.attack_cooldown == 0;
+                if attack {
+                    player.attack_cooldown = ATTACK_COOLDOWN;
+                }
+                attack
+            };
+
+            if attack && self.ecs.get::<&Health>(target).is_ok() {
+                let amount = self.held_tool()?.map(|tool| tool.damage).unwrap_or(1);
+                self.damage(target, Damage {
+                    amount,
+                    source: Some(self.player),
+                });
+                self.wear_held_tool()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // How much faster than by hand the player breaks a type of block.
+    fn mining_speed(&self, type_id: GameObjectTypeId) -> Result<PosType> {
+        let block = self.types.from_id(type_id);
+
+        Ok(match self.held_tool()? {
+            Some(tool) if tool.effective.iter().any(|tag| block.has_tag(tag)) =>
+                1.0 + TIER_SPEED * tool.tier as PosType,
+            _ => 1.0,
+        })
+    }
+
+    fn held_tool(&self) -> Result<Option<Tool>> {
+        let slot = self.ecs.get::<&Player>(self.player)?.selected_slot;
+        let inventory = self.ecs.get::<&Inventory>(self.player)?;
+
+        Ok(inventory.items.get(slot)
+            .and_then(|item| item.as_ref())
+            .filter(|item| self.types.contains(item.key))
+            .and_then(|item| self.types.from_id(self.types.get_id(item.key)).tool))
+    }
+
+    // Uses up some of the held tool, which breaks once it's worn out.
+    fn wear_held_tool(&mut self) -> Result<()> {
+        let Some(tool) = self.held_tool()? else { return Ok(()) };
+
+        let slot = self.ecs.get::<&Player>(self.player)?.selected_slot;
+        let mut inventory = self.ecs.get::<&mut Inventory>(self.player)?;
+
+        if let Some(item) = &mut inventory.items[slot] {
+            let left = item.durability().unwrap_or(tool.durability).saturating_sub(1);
+            item.set_durability(left);
+
+            if left == 0 {
+                inventory.take_from_slot(slot, 1)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn break_block(&mut self, id: EntityId) -> Result<()> {
+        let type_id = self.ecs.get::<&GameObjectTypeComponent>(id)?.id;
+        let pos = (*self.ecs.get::<&Position>(id)?).clone();
+
+        self.emit(GameEvent::BlockBroken {
+            key: self.types.from_id(type_id).key,
+            pos: pos.clone(),
+            by: Some(self.player),
+        });
+
+        self.spill_inventory(id)?;
+        self.give_drops(type_id, Some(self.player), &pos)?;
+        self.despawn(id)
+    }
+
+    // Puts the drops of a type into the inventory of `receiver`, and spills
+    // whatever doesn't fit into the world at `pos`.
+    pub(super) fn give_drops(
+            &mut self,
+            type_id: GameObjectTypeId,
+            receiver: Option<EntityId>,
+            pos: &Position) -> Result<()> {
+
+        for (key, amount) in self.types.from_id(type_id).drops {
+            let left = if let Some(receiver) = receiver
+                    && let Ok(mut inventory) = self.ecs.get::<&mut Inventory>(receiver) {
+                inventory.try_receive(&self.types, key, *amount)
+            } else {
+                *amount
+            };
+
+            if left > 0 {
+                self.spill(InventoryItem::new(key, left), pos)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_blocks_in_reach_are_mined() {
+        let mut game = Game::new();
+        game.player = game.spawn("player", (Inventory::new(10),)).unwrap();
+        let near = game.spawn("boulder", (Position::tile(ChunkPos::new(0, 0), 2, 0),)).unwrap();
+        let far = game.spawn("boulder", (Position::tile(ChunkPos::new(1, 0), 0, 0),)).unwrap();
+
+        let mining = |game: &Game| game.ecs.get::<&Player>(game.player).unwrap().mining.is_some();
+
+        game.update_attack(Some(Selected::Entity(far))).unwrap();
+        assert!(!mining(&game));
+
+        game.update_attack(Some(Selected::Entity(near))).unwrap();
+        assert!(mining(&game));
+
+        game.update_attack(Some(Selected::Entity(far))).unwrap();
+        assert!(!mining(&game));
+    }
+}
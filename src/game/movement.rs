@@ -0,0 +1,175 @@
+use super::*;
+use crate::constants::TICK_DURATION;
+
+// Keeps moving entities from ending up exactly on the edge of what they ran
+// into, where rounding could make them overlap it.
+const SKIN: PosType = 0.001;
+
+// Collision boxes are assumed to be at most this wide, so that nearby
+// entities can be looked up in the spatial index.
+const MAX_SIZE: PosType = 2.0;
+
+#[derive(Clone, Copy)]
+struct Bounds {
+    x1: PosType,
+    y1: PosType,
+    x2: PosType,
+    y2: PosType,
+}
+
+impl Bounds {
+    fn around(x: PosType, y: PosType, collision: &Collision) -> Self {
+        Self {
+            x1: x - collision.width / 2.0,
+            y1: y - collision.height / 2.0,
+            x2: x + collision.width / 2.0,
+            y2: y + collision.height / 2.0,
+        }
+    }
+
+    fn moved(&self, dx: PosType, dy: PosType) -> Self {
+        Self {
+            x1: self.x1 + dx,
+            y1: self.y1 + dy,
+            x2: self.x2 + dx,
+            y2: self.y2 + dy,
+        }
+    }
+
+    fn union(&self, other: &Self) -> Self {
+        Self {
+            x1: self.x1.min(other.x1),
+            y1: self.y1.min(other.y1),
+            x2: self.x2.max(other.x2),
+            y2: self.y2.max(other.y2),
+        }
+    }
+
+    fn overlaps(&self, other: &Self) -> bool {
+        self.x1 < other.x2 && other.x1 < self.x2 && self.y1 < other.y2 && other.y1 < self.y2
+    }
+}
+
+impl<'a> Game<'a> {
+    // Moves every entity that has a velocity, in tiles per second.
+    pub(super) fn apply_velocities(&mut self) -> Result<()> {
+        let moving: Vec<(EntityId, PosType, PosType)> = self.ecs.query::<&Velocity>()
+            .iter()
+            .filter(|(_, velocity)| velocity.x != 0.0 || velocity.y != 0.0)
+            .map(|(id, velocity)| (id, velocity.x, velocity.y))
+            .collect();
+
+        for (id, dx, dy) in moving {
+            self.move_entity(id, dx * TICK_DURATION, dy * TICK_DURATION)?;
+        }
+
+        Ok(())
+    }
+
+    // Moves a free entity, stopping it at solid tiles and entities. The axes
+    // are moved one after the other, so that an entity running into a wall
+    // at an angle slides along it. Things the entity already overlaps don't
+    // stop it, so it can't get stuck in them.
+    pub fn move_entity(&mut self, id: EntityId, dx: PosType, dy: PosType) -> Result<()> {
+        let mut pos = (*self.ecs.get::<&Position>(id)?).clone();
+        self.previous.entry(id).or_insert_with(|| pos.clone());
+
+        if let Some(collision) = self.get_collision(id) {
+            let origin = pos.chunk();
+            let bounds = Bounds::around(pos.x(&origin), pos.y(&origin), &collision);
+
+            let dx = self.allowed_move(id, &origin, bounds, dx, 0.0);
+            let bounds = bounds.moved(dx, 0.0);
+            let dy = self.allowed_move(id, &origin, bounds, 0.0, dy);
+
+            pos.move_x(dx);
+            pos.move_y(dy);
+        } else {
+            pos.move_x(dx);
+            pos.move_y(dy);
+        }
+
+        self.spatial.update(id, &pos);
+        *self.ecs.get::<&mut Position>(id)? = pos;
+
+        Ok(())
+    }
+
+    pub(super) fn get_collision(&self, id: EntityId) -> Option<Collision> {
+        let type_id = self.ecs.get::<&GameObjectTypeComponent>(id).ok()?.id;
+        self.types.from_id(type_id).collision
+    }
+
+    // How far along one axis the bounds can move before running into
+    // something solid.
+    fn allowed_move(
+            &self,
+            id: EntityId,
+            origin: &ChunkPos,
+            bounds: Bounds,
+            dx: PosType,
+            dy: PosType) -> PosType {
+
+        let target = bounds.moved(dx, dy);
+        let obstacles = self.obstacles(id, origin, bounds.union(&target));
+
+        let mut allowed = if dx != 0.0 { dx } else { dy };
+        for obstacle in obstacles {
+            if obstacle.overlaps(&bounds) || !obstacle.overlaps(&target) {
+                continue
+            }
+
+            allowed = match (dx > 0.0, dx < 0.0, dy > 0.0) {
+                (true, _, _) => allowed.min(obstacle.x1 - bounds.x2 - SKIN).max(0.0),
+                (_, true, _) => allowed.max(obstacle.x2 - bounds.x1 + SKIN).min(0.0),
+                (_, _, true) => allowed.min(obstacle.y1 - bounds.y2 - SKIN).max(0.0),
+                _            => allowed.max(obstacle.y2 - bounds.y1 + SKIN).min(0.0),
+            };
+        }
+
+        allowed
+    }
+
+    // The solid things within an area, relative to `origin`. Chunks that
+    // aren't loaded yet count as solid, so nothing walks into them.
+    fn obstacles(&self, id: EntityId, origin: &ChunkPos, area: Bounds) -> Vec<Bounds> {
+        let mut obstacles = Vec::new();
+
+        // tiles cover half a tile around their position
+        let full_tile = Collision::solid(1.0, 1.0);
+        for tx in (area.x1 + 0.5).floor() as i32..=(area.x2 + 0.5).floor() as i32 {
+            for ty in (area.y1 + 0.5).floor() as i32..=(area.y2 + 0.5).floor() as i32 {
+                let (x, y) = (tx as PosType, ty as PosType);
+                let pos = Position::tile_containing(origin, x, y);
+
+                let collision = match self.get_tile(&pos) {
+                    Some(tile) => self.types.from_id(tile.id).collision,
+                    None => Some(full_tile),
+                };
+
+                if let Some(collision) = collision && collision.solid {
+                    obstacles.push(Bounds::around(x, y, &collision));
+                }
+            }
+        }
+
+        let margin = MAX_SIZE / 2.0;
+        let nearby = self.spatial.in_rect(
+            origin,
+            area.x1 - margin,
+            area.y1 - margin,
+            area.x2 + margin,
+            area.y2 + margin);
+
+        for other in nearby {
+            if other == id { continue }
+
+            if let Some(collision) = self.get_collision(other) && collision.solid
+                    && let Ok(pos) = self.ecs.get::<&Position>(other) {
+                obstacles.push(Bounds::around(pos.x(origin), pos.y(origin), &collision));
+            }
+        }
+
+        obstacles
+    }
+}
@@ -0,0 +1,77 @@
+use super::*;
+
+const REACH: PosType = 5.0; // how far from the player blocks can be placed or used
+
+impl<'a> Game<'a> {
+    // Places the block of the held item on the selected tile.
+    pub(super) fn use_held_item(&mut self) -> Result<()> {
+        let (slot, target) = {
+            let player = self.ecs.get::<&Player>(self.player)?;
+            (player.selected_slot, player.selected.clone())
+        };
+
+        let pos = if let Some(Selected::Tile(pos)) = target {
+            pos } else { return Ok(()) };
+
+        let item = self.ecs.get::<&Inventory>(self.player)?
+            .items.get(slot).cloned().flatten();
+
+        let key = if let Some(item) = item && self.types.contains(item.key) {
+            item.key } else { return Ok(()) };
+
+        let block = if let Some(block) = self.types.from_id(self.types.get_id(key)).places() {
+            block } else { return Ok(()) };
+
+        if !self.in_reach(&pos)? || !self.tile_is_free(&pos) {
+            return Ok(())
+        }
+
+        self.spawn(block, (pos,))?;
+
+        self.ecs.get::<&mut Inventory>(self.player)?.take_from_slot(slot, 1)?;
+
+        Ok(())
+    }
+
+    pub(super) fn in_reach(&self, pos: &Position) -> Result<bool> {
+        let player = self.ecs.get::<&Position>(self.player)?;
+        let origin = player.chunk();
+
+        let dx = pos.x(&origin) - player.x(&origin);
+        let dy = pos.y(&origin) - player.y(&origin);
+
+        Ok(dx * dx + dy * dy <= REACH * REACH)
+    }
+
+    // Whether a block could be placed on a tile of the tile grid: its chunk
+    // has to be loaded, the ground can't be liquid, and nothing can be on it
+    // or overlap it.
+    fn tile_is_free(&self, pos: &Position) -> bool {
+        match self.get_tile(pos) {
+            Some(tile) if !self.types.from_id(tile.id).has_tag("liquid") => {},
+            _ => return false,
+        }
+
+        let origin = pos.chunk();
+        let (x, y) = (pos.x(&origin), pos.y(&origin));
+
+        self.spatial.in_rect(&origin, x - 1.5, y - 1.5, x + 1.5, y + 1.5)
+            .into_iter()
+            .all(|id| {
+                let Ok(other) = self.ecs.get::<&Position>(id) else { return true };
+                if other.is_tile() {
+                    return other.chunk() != origin || other.local() != pos.local()
+                }
+
+                let (ox, oy) = (other.x(&origin), other.y(&origin));
+
+                // free entities block the tile if their collision box overlaps it
+                match self.get_collision(id) {
+                    Some(collision) if collision.solid =>
+                        (ox - x).abs() >= (collision.width + 1.0) / 2.0
+                            || (oy - y).abs() >= (collision.height + 1.0) / 2.0,
+                    _ => true,
+                }
+            })
+    }
+}
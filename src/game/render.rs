@@ -2,18 +2,62 @@ use super::*;
 use crate::prelude::*;
 use crate::textures::copy_texture;
 use crate::components::*;
+use crate::constants::{CHUNK_SIZE, HOTBAR_SIZE};
+use crate::crafting::Ingredient;
 
 use sdl2::pixels::Color;
 use sdl2::rect::Rect;
 
+const CRACKS: [&str; 4] = ["crack_0", "crack_1", "crack_2", "crack_3"];
+const HOTBAR_SLOT: i32 = 40; // pixels
+const HOTBAR_MARGIN: i32 = 8;
+
 impl<'a> Game<'a> {
-    pub fn render(&mut self, canvas: &mut Canvas) -> Result<()> {
+    // `interpolation` is how far into the next tick the frame is, from 0
+    // to 1.
+    pub fn render(&mut self, canvas: &mut Canvas, interpolation: PosType) -> Result<()> {
+        self.interpolation = interpolation;
+
         let timer = debug::Timer::new("rendering");
+        for pos in &self.loaded.chunks {
+            let chunk = if let Some(chunk) = self.chunks.get(pos) {
+                chunk
+            } else {
+                // still being generated
+                let corner = self.pos_to_sdl_rect(&Position::tile(pos.clone(), 0, 0))?;
+                let size = self.tile_scale * CHUNK_SIZE as u32;
+                canvas.set_draw_color(Color::RGB(20, 20, 20));
+                canvas.fill_rect(Rect::new(corner.x(), corner.y(), size, size))
+                    .map_err(conv_err!())?;
+                continue
+            };
+
+            for (col, row, tile) in chunk.iter() {
+                let rect = self.pos_to_sdl_rect(&Position::tile(pos.clone(), col, row))?;
+
+                if let Some(texture_id) = self.types.from_id(tile.id).texture {
+                    copy_texture(
+                        canvas,
+                        &self.textures,
+                        texture_id,
+                        Some(&TextureTransform::new().rotated(tile.state)),
+                        rect
+                    ).map_err(conv_err!())?;
+                }
+            }
+        }
+
         for id in &self.loaded.ids {
             let rect = if let Ok(rect) = self.get_sdl_rect(*id) {
                 rect } else { continue };
 
-            if let Some(texture_id) = self.get_gameobjtype(*id).texture {
+            let texture = if let Ok(dropped) = self.ecs.get::<&DroppedItem>(*id) {
+                self.types.texture(dropped.item.key)
+            } else {
+                self.get_gameobjtype(*id).texture
+            };
+
+            if let Some(texture_id) = texture {
                 copy_texture(
                     canvas,
                     &self.textures,
@@ -23,11 +67,36 @@ impl<'a> Game<'a> {
                 ).map_err(conv_err!())?;
             }
         }
+
+        // health bars of damaged entities, on top of everything
+        for id in &self.loaded.ids {
+            let health = if let Ok(health) = self.ecs.get::<&Health>(*id) {
+                health.clone() } else { continue };
+
+            if health.current < health.max
+                    && let Ok(rect) = self.get_sdl_rect(*id) {
+                let width = rect.width() * health.current as u32 / health.max as u32;
+                let bar = Rect::new(rect.x(), rect.y() - 6, rect.width(), 4);
+
+                canvas.set_draw_color(Color::RGB(60, 0, 0));
+                canvas.fill_rect(bar).map_err(conv_err!())?;
+                canvas.set_draw_color(Color::RGB(220, 30, 30));
+                canvas.fill_rect(Rect::new(bar.x(), bar.y(), width, 4)).map_err(conv_err!())?;
+            }
+        }
         timer.done();
 
         if let Ok(player) = self.ecs.get::<&Player>(self.player) {
-            if let Some(selected) = player.selected {
-                if let Ok(rect) = self.get_sdl_rect(selected) {
+            if let Some(mining) = &player.mining
+                    && let Ok(rect) = self.get_sdl_rect(mining.target) {
+                let stage = ((mining.progress * CRACKS.len() as PosType) as usize)
+                    .min(CRACKS.len() - 1);
+                copy_texture(canvas, &self.textures, CRACKS[stage], None, rect)
+                    .map_err(conv_err!())?;
+            }
+
+            if let Some(selected) = &player.selected {
+                if let Ok(rect) = self.selected_sdl_rect(selected) {
                     canvas.set_draw_color(Color::RGB(255, 255, 255));
                     canvas.draw_lines([
                         rect.top_left(),
@@ -45,6 +114,22 @@ impl<'a> Game<'a> {
         Ok(())
     }
 
+    // Like "Chest (1) <- Wood (8)".
+    fn recipe_text(&self, recipe: &Recipe) -> String {
+        let outputs: Vec<String> = recipe.outputs.iter()
+            .map(|item| format!("{} ({})", self.types.name(item.key), item.amount))
+            .collect();
+
+        let inputs: Vec<String> = recipe.inputs.iter()
+            .map(|input| match &input.ingredient {
+                Ingredient::Item(key) => format!("{} ({})", self.types.name(key), input.amount),
+                Ingredient::Tag(tag) => format!("any {} ({})", tag, input.amount),
+            })
+            .collect();
+
+        format!("{} <- {}", outputs.join(", "), inputs.join(", "))
+    }
+
     pub fn render_ui(&mut self, canvas: &mut Canvas) -> Result<()> {
         use crate::ui::*;
 
@@ -54,15 +139,117 @@ impl<'a> Game<'a> {
                 drawer.fill_bg(canvas, &mut self.font, (0, 0, 0))?;
                 if let Ok(inventory) = self.ecs.get::<&Inventory>(self.player) {
                     for (i, item) in inventory.items.iter().enumerate() {
-                        if let Some(item) = item {
-                            let text = format!("{}: {} ({})\n", i, item.key, item.amount);
-                            drawer.text(canvas, &mut self.font, text.into())?;
-                        }
+                        let text = match item {
+                            Some(item) => format!("{}: {} ({})\n", i, self.types.name(item.key), item.amount),
+                            None => format!("{}: -\n", i),
+                        };
+                        drawer.text(canvas, &mut self.font, text.into())?;
+                    }
+                }
+
+                // the crafting list on the right half, one recipe per line
+                let half = self.font.px_to_ch_x(inventory.to_rect(self.screen_size).width() / 2);
+                drawer.text_at(canvas, &mut self.font, half as i32, 0, "Crafting".into())?;
+
+                for (line, recipe) in self.craftable()?.into_iter().enumerate() {
+                    let text = self.recipe_text(&self.recipes[recipe]);
+                    drawer.text_at(canvas, &mut self.font, half as i32, line as i32 + 1, text.into())?;
+                }
+            }
+        }
+
+        if let Some(ui) = self.ui_handler.get("container")
+                && ui.visible
+                && let Some(container) = self.open_container {
+
+            let rect = ui.to_rect(self.screen_size);
+            let mut drawer = tui::TUIDrawer::new(rect);
+            drawer.fill_bg(canvas, &mut self.font, (0, 0, 0))?;
+
+            // the container on the left, the player on the right, one slot
+            // per line
+            let half = self.font.px_to_ch_x(rect.width() / 2) as i32;
+            let title = self.ecs.get::<&GameObjectTypeComponent>(container)
+                .map(|type_id| self.types.from_id(type_id.id).name)
+                .unwrap_or("Container");
+            let panes = [
+                (container, 0, title),
+                (self.player, half, "Inventory"),
+            ];
+
+            for (id, x, title) in panes {
+                drawer.text_at(canvas, &mut self.font, x, 0, title.into())?;
+
+                if let Ok(inventory) = self.ecs.get::<&Inventory>(id) {
+                    for (i, item) in inventory.items.iter().enumerate() {
+                        let text = match item {
+                            Some(item) => format!("{} ({})", self.types.name(item.key), item.amount),
+                            None => "-".to_string(),
+                        };
+                        drawer.text_at(canvas, &mut self.font, x, i as i32 + 1, text.into())?;
                     }
                 }
             }
         }
-        
+
+        self.render_hotbar(canvas).context("rendering hotbar")?;
+
+        Ok(())
+    }
+
+    // The hotbar slots in a strip at the bottom of the screen, with the held
+    // one outlined.
+    fn render_hotbar(&mut self, canvas: &mut Canvas) -> Result<()> {
+        let selected = self.ecs.get::<&Player>(self.player)?.selected_slot;
+        let items: Vec<Option<InventoryItem>> = match self.ecs.get::<&Inventory>(self.player) {
+            Ok(inventory) => inventory.items.iter().take(HOTBAR_SIZE).cloned().collect(),
+            Err(_) => return Ok(()),
+        };
+
+        let x = (self.screen_size.0 - HOTBAR_SLOT * HOTBAR_SIZE as i32) / 2;
+        let y = self.screen_size.1 - HOTBAR_SLOT - HOTBAR_MARGIN;
+        let size = HOTBAR_SLOT as u32;
+
+        for (i, item) in items.into_iter().enumerate() {
+            let rect = Rect::new(x + i as i32 * HOTBAR_SLOT, y, size, size);
+
+            canvas.set_draw_color(Color::RGB(30, 30, 30));
+            canvas.fill_rect(rect).map_err(conv_err!())?;
+
+            if let Some(item) = item {
+                if let Some(texture_id) = self.types.texture(item.key) {
+                    let icon = Rect::from_center(rect.center(), size * 3 / 4, size * 3 / 4);
+                    copy_texture(canvas, &self.textures, texture_id, None, icon)
+                        .map_err(conv_err!())?;
+                }
+
+                // how worn out a used tool is
+                if let Some(left) = item.durability()
+                        && self.types.contains(item.key)
+                        && let Some(tool) = self.types.from_id(self.types.get_id(item.key)).tool {
+                    let width = (size - 8) * left as u32 / tool.durability.max(1) as u32;
+                    canvas.set_draw_color(Color::RGB(60, 60, 60));
+                    canvas.fill_rect(Rect::new(rect.x() + 4, rect.bottom() - 7, size - 8, 3))
+                        .map_err(conv_err!())?;
+                    canvas.set_draw_color(Color::RGB(60, 200, 60));
+                    canvas.fill_rect(Rect::new(rect.x() + 4, rect.bottom() - 7, width, 3))
+                        .map_err(conv_err!())?;
+                }
+
+                if item.amount > 1 {
+                    let mut drawer = tui::TUIDrawer::new(rect);
+                    drawer.text_at(canvas, &mut self.font, 0, 0, item.amount.to_string().into())?;
+                }
+            }
+
+            canvas.set_draw_color(if i == selected {
+                Color::RGB(255, 255, 255)
+            } else {
+                Color::RGB(80, 80, 80)
+            });
+            canvas.draw_rect(rect).map_err(conv_err!())?;
+        }
+
         Ok(())
     }
 }
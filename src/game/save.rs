@@ -0,0 +1,86 @@
+use super::*;
+use crate::prelude::*;
+use crate::components::*;
+use crate::save::{self, region, SavedWorld, SAVE_VERSION};
+
+use std::path::Path;
+
+impl<'a> Game<'a> {
+    pub fn save(&mut self, dir: &Path) -> Result<()> {
+        let timer = debug::Timer::new("saving world");
+
+        let world = SavedWorld {
+            version: SAVE_VERSION,
+            seed: self.worldgen.worldgen().seed(),
+            tick: self.tick,
+            next_uid: self.next_uid,
+            player: SavedEntity::new(&self.ecs, &self.types, self.player)?,
+        };
+
+        self.flush_event_log()?;
+
+        let mut loaded = HashMap::new();
+
+        for (pos, chunk) in &self.chunks {
+            let mut entities = Vec::new();
+            for id in self.spatial.in_chunk(pos) {
+                if *id == self.player { continue }
+                entities.push(SavedEntity::new(&self.ecs, &self.types, *id)?);
+            }
+
+            loaded.insert(pos.clone(), SavedChunk::new(chunk, &self.types, entities));
+        }
+
+        region::write_chunks(dir, loaded.iter().chain(self.unloaded.iter()))?;
+
+        // last, so the world file never refers to chunks that weren't written
+        save::write_world(dir, &world)?;
+
+        // unloaded chunks are on disk now and can be read back from there
+        if self.save_dir.as_deref() == Some(dir) {
+            self.unloaded.clear();
+        }
+        self.save_dir = Some(dir.to_path_buf());
+
+        timer.done();
+
+        Ok(())
+    }
+
+    pub fn load(&mut self, dir: &Path) -> Result<()> {
+        let world = save::read_world(dir)?;
+
+        self.ecs.clear();
+        self.spatial.clear();
+        self.chunks.clear();
+        self.unloaded.clear();
+        self.previous.clear();
+        self.save_dir = Some(dir.to_path_buf());
+        self.start_worldgen(world.seed);
+        self.tick = world.tick;
+        self.next_uid = world.next_uid;
+        self.player = world.player.restore(&mut self.ecs, &self.types)
+            .context("restoring player")?;
+        self.assign_uid(self.player)?;
+        self.index_entity(self.player);
+
+        self.update_loaded(true)?;
+        self.init_ui();
+
+        Ok(())
+    }
+
+    // Reads a chunk saved in a region file. Chunks that can't be read are
+    // skipped, so they get generated again instead.
+    pub(super) fn read_saved_chunk(&self, pos: &ChunkPos) -> Option<SavedChunk> {
+        let dir = self.save_dir.as_ref()?;
+
+        match region::read_chunk(dir, pos) {
+            Ok(chunk) => chunk,
+            Err(err) => {
+                eprintln!("error: reading chunk {:?}: {}, generating it again", pos, err);
+                None
+            }
+        }
+    }
+}
@@ -2,8 +2,8 @@ use super::*;
 use crate::prelude::*;
 use crate::components::*;
 use crate::constants::*;
-use crate::gameobjtype::UpdateData;
-use crate::event::ActionUpdates;
+use crate::gameobjtype::{UpdateData, Command};
+use crate::event::SLOT_BINDS;
 
 use sdl2::event::{
     Event,
@@ -15,45 +15,46 @@ use hecs::Entity as EntityId;
 
 use std::cmp::Ordering;
 
-fn handle_err(label: &str, res: std::result::Result<(), impl std::fmt::Display>) {
+pub(super) fn handle_err(label: &str, res: std::result::Result<(), impl std::fmt::Display>) {
     if let Err(res) = res {
         eprintln!("error: {}: {}", label, res);
     }
 }
 
 impl<'a> Game<'a> {
-    pub fn update(&mut self, event_pump: &mut EventPump) -> Result<bool> {
+    // Collects the input since the last frame, which is applied on the next
+    // tick. Returns whether the game should quit.
+    pub fn handle_events(&mut self, event_pump: &mut EventPump) -> bool {
         let timer = debug::Timer::new("handling events");
-        let mut updates = ActionUpdates::new();
 
         for event in event_pump.poll_iter() {
             match event {
                 Event::Quit { .. } => {
-                    return Ok(true);
+                    return true;
                 },
                 Event::MouseWheel { y, .. } => {
-                    self.tile_scale =
-                        (self.tile_scale as i32).saturating_sub(y).min(70).max(30) as u32;
+                    self.scrolled += y;
                 },
                 Event::KeyDown { scancode, .. } => {
                     if let Some(scancode) = scancode {
-                        updates.register_event(&self.action_handler, scancode, true); }
+                        self.actions.register_event(&self.action_handler, scancode, true); }
                 },
                 Event::KeyUp { scancode, .. } => {
                     if let Some(scancode) = scancode {
-                        updates.register_event(&self.action_handler, scancode, false); }
+                        self.actions.register_event(&self.action_handler, scancode, false); }
                 },
                 Event::MouseButtonDown { mouse_btn, .. } => {
-                    updates.register_event(&self.action_handler, mouse_btn, true);
+                    self.actions.register_event(&self.action_handler, mouse_btn, true);
                 },
                 Event::MouseButtonUp { mouse_btn, .. } => {
-                    updates.register_event(&self.action_handler, mouse_btn, false);
+                    self.actions.register_event(&self.action_handler, mouse_btn, false);
                 },
                 Event::Window { win_event, .. } => {
                     match win_event {
+                        // the rest of the events are left for the next frame,
+                        // input from earlier ones is kept
                         WindowEvent::Resized(width, height) => {
                             self.screen_size = (width, height);
-                            updates.clear();
                             break
                         },
                         _ => {}
@@ -63,16 +64,36 @@ impl<'a> Game<'a> {
             }
         }
 
+        // the mouse and the ui are looked at once per frame, however many
+        // ticks it has
+        let mouse = event_pump.mouse_state();
+        self.mouse = (mouse.x(), mouse.y());
+        self.ui_hovered = self.ui_handler.update(event_pump, self.screen_size);
+
         timer.done();
 
+        false
+    }
+
+    // Advances the world by one tick of `TICK_DURATION`.
+    pub fn tick(&mut self) -> Result<()> {
+        self.tick += 1;
+        self.previous.clear();
+
         if let Ok(mut player) = self.ecs.get::<&mut Player>(self.player) {
-            player.action_state.update(&self.action_handler, &updates);
+            player.action_state.update(&self.action_handler, &self.actions);
         }
+        self.actions.clear();
+        let scrolled = std::mem::take(&mut self.scrolled);
 
-        self.update_loaded(false)?;
+        // chunks from the worldgen threads are only merged here, between
+        // ticks
+        let received = self.receive_chunks()?;
+        self.update_loaded(received)?;
 
-        let ui_hovered = self.update_ui(event_pump);
-        self.update_player(event_pump, ui_hovered)?;
+        self.update_ui();
+        self.update_player(scrolled)?;
+        self.apply_damage();
 
         let timer = debug::Timer::new("getting update fns");
         let mut id_update_fn_pairs = Vec::new();
@@ -80,82 +101,190 @@ impl<'a> Game<'a> {
             if let Ok(update_fn) = self.ecs.get::<&UpdateFn>(*id) {
                 id_update_fn_pairs.push((
                     *id,
+                    self.uid(*id),
                     self.types.get_update_fn_from_id(update_fn.id)
                 ));
             }
         }
         timer.done();
 
-        let update_data = UpdateData{};
+        let mut update_data = UpdateData::new(
+            self.tick,
+            self.worldgen.worldgen().seed(),
+            self.player,
+            &self.types,
+            &self.spatial,
+            &self.chunks);
 
         let timer = debug::Timer::new("updating");
-        for (id, update_fn) in id_update_fn_pairs {
+        let updated: Vec<EntityId> = id_update_fn_pairs.iter().map(|(id, ..)| *id).collect();
+        for (id, uid, update_fn) in id_update_fn_pairs {
+            update_data.for_entity(uid);
             handle_err(&format!("updating entity {}", id.id()).to_string(),
-                update_fn(&mut self.ecs, id, &update_data));
+                update_fn(&mut self.ecs, id, &mut update_data));
+        }
+        let commands = update_data.commands;
+        for id in updated {
+            self.spatial.refresh(id, &self.ecs);
+        }
+        timer.done();
+
+        let timer = debug::Timer::new("applying commands");
+        for command in commands {
+            handle_err("applying command", self.apply_command(command));
         }
         timer.done();
 
-        Ok(false)
+        let timer = debug::Timer::new("moving");
+        self.apply_velocities()?;
+        timer.done();
+
+        let timer = debug::Timer::new("updating items");
+        self.update_items()?;
+        timer.done();
+
+        let timer = debug::Timer::new("handling events");
+        self.dispatch_events();
+        self.update_event_log()?;
+        timer.done();
+
+        Ok(())
+    }
+
+    pub(super) fn apply_command(&mut self, command: Command) -> Result<()> {
+        match command {
+            Command::Spawn { key, pos } => {
+                if !self.types.contains(key) {
+                    bail!("can't spawn unknown type '{}'", key)
+                }
+                self.spawn(key, (pos,))?;
+            },
+            // it might have been despawned already
+            Command::Despawn(id) => if self.ecs.contains(id) {
+                self.despawn(id)?;
+            },
+            Command::Emit(event) => self.emit(event),
+        }
+
+        Ok(())
     }
 
-    fn update_player(&mut self, event_pump: &mut EventPump, ui_hovered: bool) -> Result<()> {
+    fn update_player(&mut self, scrolled: i32) -> Result<()> {
+        let ui_hovered = self.ui_hovered;
+        let (mouse_x, mouse_y) = self.mouse;
         let actions = self.ecs.get::<&Player>(self.player)?.action_state.clone();
 
+        // the mouse wheel zooms while the modifier is held, and scrolls
+        // through the hotbar otherwise
+        if actions.key("zoom") {
+            self.tile_scale =
+                (self.tile_scale as i32).saturating_sub(scrolled).min(70).max(30) as u32;
+        } else if scrolled != 0 {
+            let mut player = self.ecs.get::<&mut Player>(self.player)?;
+            player.selected_slot = (player.selected_slot as i32 - scrolled)
+                .rem_euclid(HOTBAR_SIZE as i32) as usize;
+        }
+
+        for (slot, (_, key, _)) in SLOT_BINDS.iter().enumerate() {
+            if actions.key(key) {
+                self.ecs.get::<&mut Player>(self.player)?.selected_slot = slot;
+            }
+        }
+
+        // tiles per second
         let speed =
             if actions.key("run")
-                { 0.2 } else { 0.1 };
+                { 12.0 } else { 6.0 };
+
+        if let Ok(mut velocity) = self.ecs.get::<&mut Velocity>(self.player) {
+            *velocity = Velocity::default();
+            if actions.key("move_right") { velocity.x += speed; }
+            if actions.key("move_left")  { velocity.x -= speed; }
+            if actions.key("move_down")  { velocity.y += speed; }
+            if actions.key("move_up")    { velocity.y -= speed; }
+        }
+
+        let selected =
+            if actions.key("attack") && !ui_hovered {
+                self.ecs.get::<&Player>(self.player)?.selected.clone()
+            } else { None };
 
-        if let Ok(mut pos) = self.ecs.get::<&mut Position>(self.player) {
-            if actions.key("move_right") { pos.move_x(speed); }
-            if actions.key("move_left")  { pos.move_x(-speed); }
-            if actions.key("move_down")  { pos.move_y(speed); }
-            if actions.key("move_up")    { pos.move_y(-speed); }
+        self.update_attack(selected)?;
+
+        self.update_container()?;
+
+        if actions.key("use") {
+            if ui_hovered {
+                self.click_container(mouse_x, mouse_y)?;
+                self.click_crafting(mouse_x, mouse_y)?;
+                self.click_inventory(mouse_x, mouse_y, actions.key("run"))?;
+            } else if !self.try_open_container()? {
+                self.use_held_item()?;
+            }
+        }
+
+        if actions.key("drop") {
+            self.drop_held_stack()?;
+        }
+
+        let inventory_open = self.ui_handler.get("inventory").is_some_and(|ui| ui.visible);
+        if actions.key("sort_inventory") && inventory_open {
+            self.sort_inventory()?;
         }
 
         if !ui_hovered {
-            let selected =
-                if actions.key("attack") {
-                    if let Ok(player) = self.ecs.get::<&Player>(self.player) {
-                        player.selected
-                    } else { None }
-                } else { None };
-
-            if let Some(selected) = selected {
-                handle_err("despawning selected entity",
-                    self.ecs.despawn(selected).into());
-            }
+            let selected = self.hovered(mouse_x, mouse_y);
 
             if let Ok(mut player) = self.ecs.get::<&mut Player>(self.player) {
-                player.selected = 'block: {
-                    let mouse = event_pump.mouse_state();
-
-                    for i in (0..self.loaded.ids.len()).rev() {
-                        let id = self.loaded.ids[i];
-                        if id == self.player { continue }
-                        if let Ok(rect) = self.get_sdl_rect(id) {
-                            if rect.contains_point((mouse.x(), mouse.y())) {
-                                break 'block Some(id);
-                            }
-                        }
-                    }
-
-                    None
-                };
+                player.selected = selected;
             }
         }
 
         Ok(())
     }
 
+    // The topmost thing at a point on the screen.
+    fn hovered(&self, x: i32, y: i32) -> Option<Selected> {
+        let (x, y, camera) = {
+            let player = self.ecs.get::<&Position>(self.player).ok()?;
+            let camera = player.chunk();
+            let scale = self.tile_scale as PosType;
+            (
+                player.x(&camera) + (x - self.screen_size.0 / 2) as PosType / scale,
+                player.y(&camera) + (y - self.screen_size.1 / 2) as PosType / scale,
+                camera,
+            )
+        };
+
+        // entities are one tile large, so look for positions within half a
+        // tile, and pick the one drawn last
+        let topmost = self.spatial.in_rect(&camera, x - 0.5, y - 0.5, x + 0.5, y + 0.5)
+            .into_iter()
+            .filter(|id| *id != self.player)
+            .filter_map(|id| {
+                let pos = self.ecs.get::<&Position>(id).ok()?;
+                Some((id, pos.order(), pos.x(&camera) + pos.y(&camera)))
+            })
+            .max_by(|a, b| a.1.cmp(&b.1).then(a.2.total_cmp(&b.2)));
+
+        if let Some((id, _, _)) = topmost {
+            return Some(Selected::Entity(id))
+        }
+
+        let pos = Position::tile_containing(&camera, x, y);
+        self.get_tile(&pos).map(|_| Selected::Tile(pos))
+    }
+
     pub(super) fn update_loaded(&mut self, force: bool) -> Result<()> {
-        if !force && !self.loaded_update_counter.count() {
+        if !force && !self.loaded_dirty && !self.loaded_update_counter.count() {
             return Ok(())
         }
 
-        let do_update = force ||
+        let do_update = force || self.loaded_dirty ||
             self.ecs.get::<&Position>(self.player)?.clone().chunk() != self.player_chunk;
 
         if do_update {
+            self.loaded_dirty = false;
             self.player_chunk = self.ecs.get::<&Position>(self.player)?.chunk();
 
             let chunks = {
@@ -171,11 +300,23 @@ impl<'a> Game<'a> {
                 )).to_vec()
             };
 
-            let timer = debug::Timer::new("generating chunks?");
-            for chunk in &chunks {
-                if !self.chunks.contains(chunk) {
-                    self.generate_chunk(chunk.clone())?;
-                }
+            let timer = debug::Timer::new("unloading chunks");
+            let far: Vec<ChunkPos> = self.chunks.keys()
+                .filter(|chunk| chunk.distance(&self.player_chunk) > KEEP_ALIVE_DISTANCE)
+                .cloned()
+                .collect();
+            self.unload_chunks(&far)?;
+            timer.done();
+
+            let timer = debug::Timer::new("loading chunks");
+            // closest first, so those get generated first
+            let mut missing: Vec<ChunkPos> = chunks.iter()
+                .filter(|chunk| !self.chunks.contains_key(chunk))
+                .cloned()
+                .collect();
+            missing.sort_by_key(|chunk| chunk.distance(&self.player_chunk));
+            for chunk in missing {
+                self.load_chunk(chunk)?;
             }
             timer.done();
             
@@ -184,15 +325,16 @@ impl<'a> Game<'a> {
             let mut render_order: [Vec<EntityId>; 5] =
                 core::array::from_fn(|_| Vec::new());
 
-            for (id, (pos,)) in self.ecs.query::<(&Position,)>().iter() {
-                if chunks.contains(&pos.chunk()) {
+            for chunk in &chunks {
+                for id in self.spatial.in_chunk(chunk) {
+                    let pos = self.ecs.get::<&Position>(*id)?;
                     render_order[
-                        if id == self.player {
+                        if *id == self.player {
                             2
                         } else {
                             pos.order()
                         }
-                    ].push(id);
+                    ].push(*id);
                 }
             }
 
@@ -200,8 +342,8 @@ impl<'a> Game<'a> {
                 let pos1 = self.ecs.get::<&Position>(*id1).unwrap();
                 let pos2 = self.ecs.get::<&Position>(*id2).unwrap();
 
-                let pos1 = pos1.x() + pos1.y();
-                let pos2 = pos2.x() + pos2.y();
+                let pos1 = pos1.x(&self.player_chunk) + pos1.y(&self.player_chunk);
+                let pos2 = pos2.x(&self.player_chunk) + pos2.y(&self.player_chunk);
 
                 if pos1 < pos2 {
                     Ordering::Less 
@@ -215,6 +357,7 @@ impl<'a> Game<'a> {
             timer.done();
 
             self.loaded = Loaded{
+                chunks,
                 ids,
             };
         }
@@ -222,25 +365,43 @@ impl<'a> Game<'a> {
         Ok(())
     }
 
-    fn update_ui(&mut self, event_pump: &mut EventPump) -> bool {
-        use crate::ui::*;
-
-        // let mouse = event_pump.mouse_state();
-        // let mx = mouse.x();
-        // let my = mouse.y();
-        // let mouse_delta = (mx - self.last_mouse_pos.0, my - self.last_mouse_pos.1);
+    fn update_ui(&mut self) {
+        let toggle = self.ecs.get::<&Player>(self.player)
+            .is_ok_and(|player| player.action_state.key("toggle_inventory"));
 
-        if let Some(mut inventory) = self.ui_handler.get_mut("inventory") {
-            if let Ok(mut player) = self.ecs.get::<&Player>(self.player) {
-                if player.action_state.key("toggle_inventory") {
-                    inventory.visible = !inventory.visible;
-                }
+        if toggle && self.open_container.is_some() {
+            self.close_container();
+        } else if let Some(inventory) = self.ui_handler.get_mut("inventory") {
+            if toggle {
+                inventory.visible = !inventory.visible;
             }
         }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spawn_command_keeps_the_position() {
+        let mut game = Game::new();
 
-        let ui_hovered = self.ui_handler.update(event_pump, self.screen_size);
-        // self.last_mouse_pos = (mx, my);
+        for key in ["test", "player"] {
+            let pos = Position::free_in(ChunkPos::new(-2, 3), 4.5, 7.25);
+            game.apply_command(Command::Spawn { key, pos: pos.clone() }).unwrap();
+
+            let id = game.spatial.in_chunk(&ChunkPos::new(-2, 3))[0];
+            let spawned = (*game.ecs.get::<&Position>(id).unwrap()).clone();
+            assert_eq!((spawned.chunk(), spawned.local()), (pos.chunk(), pos.local()));
+            game.despawn(id).unwrap();
+        }
+    }
 
-        ui_hovered
+    #[test]
+    fn spawn_command_rejects_unknown_types() {
+        let mut game = Game::new();
+        let pos = Position::free(0.0, 0.0);
+        assert!(game.apply_command(Command::Spawn { key: "no such type", pos }).is_err());
     }
 }
@@ -0,0 +1,82 @@
+// Things that happened in the world. They're queued during a tick and handed
+// to the types that listen for them at the end of it.
+
+use crate::types::*;
+use crate::components::{Position, InventoryItem, HealthAmount, Uid};
+use crate::utils::deserialize_interned;
+
+use serde::{Serialize, Deserialize};
+
+use hecs::Entity as EntityId;
+
+// Entities are referred to by their ECS id while the game runs, and by their
+// uid once recorded, see `RecordedEvent`. Not everything has a listener yet.
+#[allow(dead_code)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum GameEvent<Id = EntityId> {
+    BlockBroken {
+        #[serde(deserialize_with = "deserialize_interned")]
+        key: Key,
+        pos: Position,
+        by: Option<Id>,
+    },
+    ItemPickedUp {
+        by: Id,
+        item: InventoryItem,
+    },
+    EntityDamaged {
+        target: Id,
+        amount: HealthAmount,
+        source: Option<Id>,
+    },
+    EntityDied {
+        target: Id,
+        #[serde(deserialize_with = "deserialize_interned")]
+        key: Key,
+    },
+    // emitted by update fns, for types to notify each other
+    Custom {
+        #[serde(deserialize_with = "deserialize_interned")]
+        key: Key,
+        source: Id,
+        target: Option<Id>,
+    },
+}
+
+impl<Id: Copy> GameEvent<Id> {
+    pub fn map_ids<T>(&self, f: impl Fn(Id) -> T) -> GameEvent<T> {
+        match self {
+            Self::BlockBroken { key, pos, by } => GameEvent::BlockBroken {
+                key,
+                pos: pos.clone(),
+                by: by.map(&f),
+            },
+            Self::ItemPickedUp { by, item } => GameEvent::ItemPickedUp {
+                by: f(*by),
+                item: item.clone(),
+            },
+            Self::EntityDamaged { target, amount, source } => GameEvent::EntityDamaged {
+                target: f(*target),
+                amount: *amount,
+                source: source.map(&f),
+            },
+            Self::EntityDied { target, key } => GameEvent::EntityDied {
+                target: f(*target),
+                key,
+            },
+            Self::Custom { key, source, target } => GameEvent::Custom {
+                key,
+                source: f(*source),
+                target: target.map(&f),
+            },
+        }
+    }
+}
+
+// An event as it's written to the event log, which stays valid across saves
+// and can be read back to replay a session.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    pub tick: u64,
+    pub event: GameEvent<Uid>,
+}
@@ -3,10 +3,13 @@
 pub use crate::prelude::*;
 pub use crate::gameobjtype::*;
 pub use crate::components::*;
+pub use crate::gameevent::*;
 
 pub use hecs::World as ECSWorld;
 pub use hecs::Entity as ECSEntityId;
 pub use hecs::EntityBuilder;
 
+pub use rand::Rng;
+
 #[allow(unused_imports)]
 pub use sdl2::keyboard::Scancode as K;
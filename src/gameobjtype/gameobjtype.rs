@@ -1,8 +1,17 @@
 use crate::gameobjtype::base::*;
 use crate::gameobjtype::types::TYPES;
+use crate::spatial::SpatialIndex;
+use crate::chunk::{Chunk, Tile};
+use crate::worldgen::hash;
+use crate::constants::{TPS, TICK_DURATION};
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
 
 use std::collections::HashMap;
 
+const DEFAULT_MAX_STACK: Amount = 64;
+
 macro_rules! setter {
     ($name:ident, $type:ty) => {
         #[allow(dead_code)]
@@ -19,7 +28,21 @@ type InitFnType = fn(
 pub type UpdateFnType = fn(
     ecs: &mut ECSWorld,
     ecs_id: ECSEntityId,
-    update_data: &UpdateData) -> Result<()>;
+    update_data: &mut UpdateData) -> Result<()>;
+
+// Runs when the health of an entity drops to zero. Entities that are still
+// dead afterwards are despawned.
+pub type DeathFnType = fn(
+    ecs: &mut ECSWorld,
+    ecs_id: ECSEntityId,
+    damage: &Damage) -> Result<()>;
+
+// Runs for every loaded entity of the type, once for each event of the tick.
+pub type EventFnType = fn(
+    ecs: &mut ECSWorld,
+    ecs_id: ECSEntityId,
+    event: &GameEvent,
+    update_data: &mut UpdateData) -> Result<()>;
 
 // type RenderFnType = fn(
 //     ecs: &ECSWorld,
@@ -28,9 +51,87 @@ pub type UpdateFnType = fn(
 //     textures: &Textures,
 //     canvas: &mut Canvas) -> Result<()>;
 
-pub struct UpdateData {
-    // pub events: EventState,
-    // TODO delta time
+// Changes to the world that update fns can't make while it's being iterated.
+// They're applied after every update fn has run.
+pub enum Command {
+    Spawn {
+        key: &'static str,
+        pos: Position,
+    },
+    Despawn(ECSEntityId),
+    Emit(GameEvent), // handled at the end of the tick
+}
+
+// What update fns can see of the world besides the ECS. Not every type
+// uses all of it.
+pub struct UpdateData<'a> {
+    pub dt: PosType, // seconds since the last tick
+    pub tick: u64,   // ticks since the world was created
+    #[allow(dead_code)]
+    pub time: f64,   // seconds since the world was created
+    pub seed: u64,
+    #[allow(dead_code)]
+    pub player: ECSEntityId,
+    pub types: &'a GameObjectTypes,
+    #[allow(dead_code)]
+    pub spatial: &'a SpatialIndex,
+    pub chunks: &'a HashMap<ChunkPos, Chunk>,
+    // different for every entity and tick, but the same for the same seed,
+    // also after loading a save
+    pub rng: StdRng,
+    pub commands: Vec<Command>,
+}
+
+impl<'a> UpdateData<'a> {
+    pub fn new(
+            tick: u64,
+            seed: u64,
+            player: ECSEntityId,
+            types: &'a GameObjectTypes,
+            spatial: &'a SpatialIndex,
+            chunks: &'a HashMap<ChunkPos, Chunk>) -> Self {
+
+        Self {
+            dt: TICK_DURATION,
+            tick,
+            time: tick as f64 / TPS as f64,
+            seed,
+            player,
+            types,
+            spatial,
+            chunks,
+            rng: StdRng::seed_from_u64(seed),
+            commands: Vec::new(),
+        }
+    }
+
+    // Reseeds the rng for the entity that is updated next.
+    pub fn for_entity(&mut self, uid: Uid) {
+        self.rng = StdRng::seed_from_u64(hash(self.seed, self.tick as i64, uid.0 as i64));
+    }
+
+    #[allow(dead_code)]
+    pub fn spawn(&mut self, key: &'static str, pos: Position) {
+        self.commands.push(Command::Spawn { key, pos });
+    }
+
+    #[allow(dead_code)]
+    pub fn despawn(&mut self, id: ECSEntityId) {
+        self.commands.push(Command::Despawn(id));
+    }
+
+    pub fn emit(&mut self, event: GameEvent) {
+        self.commands.push(Command::Emit(event));
+    }
+
+    // The tile in the tile grid at a position, if its chunk is loaded.
+    pub fn tile(&self, pos: &Position) -> Option<Tile> {
+        if let Position::Tile { chunk, col, row, .. } = pos {
+            self.chunks.get(chunk).map(|chunk| chunk.get(*col, *row))
+        } else {
+            None
+        }
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -40,6 +141,16 @@ pub struct GameObjectTypeBuilder {
     pub update: Option<UpdateFnType>,
     pub texture: Option<&'static str>,
     pub class: Option<GameObjectClass>,
+    pub collision: Option<Collision>,
+    pub health: Option<HealthAmount>, // max health
+    pub on_death: Option<DeathFnType>,
+    pub on_event: Option<EventFnType>,
+    pub hardness: Option<PosType>, // seconds to break by hand, unbreakable if not set
+    pub drops: Option<&'static [(&'static str, Amount)]>, // when broken or killed
+    pub name: Option<&'static str>, // shown to the player, the key if not set
+    pub max_stack: Option<Amount>,
+    pub tags: Option<&'static [&'static str]>,
+    pub tool: Option<Tool>,
 }
 
 impl GameObjectTypeBuilder {
@@ -50,6 +161,16 @@ impl GameObjectTypeBuilder {
             update: None,
             texture: None,
             class: None,
+            collision: None,
+            health: None,
+            on_death: None,
+            on_event: None,
+            hardness: None,
+            drops: None,
+            name: None,
+            max_stack: None,
+            tags: None,
+            tool: None,
         }
     }
 
@@ -57,12 +178,24 @@ impl GameObjectTypeBuilder {
     setter!(update, UpdateFnType);
     setter!(texture, &'static str);
     setter!(class, GameObjectClass);
+    setter!(collision, Collision);
+    setter!(health, HealthAmount);
+    setter!(on_death, DeathFnType);
+    setter!(on_event, EventFnType);
+    setter!(hardness, PosType);
+    setter!(drops, &'static [(&'static str, Amount)]);
+    setter!(name, &'static str);
+    setter!(max_stack, Amount);
+    setter!(tags, &'static [&'static str]);
+    setter!(tool, Tool);
 }
 
 #[derive(Copy, Clone)]
 pub enum GameObjectClass {
     Block {},
-    Item {},
+    Item {
+        places: Option<&'static str>, // the block placed when the item is used
+    },
     Entity {},
 }
 
@@ -71,21 +204,81 @@ impl GameObjectClass {
         Self::Block {}}
 
     pub const fn item() -> Self {
-        Self::Item {}}
+        Self::Item { places: None }}
+
+    pub const fn placeable(block: &'static str) -> Self {
+        Self::Item { places: Some(block) }}
 
     pub const fn entity() -> Self {
         Self::Entity {}}
 }
 
+// A box centered on the position of an object. Solid objects block the
+// movement of everything else that has a collision box.
+#[derive(Copy, Clone)]
+pub struct Collision {
+    pub width: PosType,
+    pub height: PosType,
+    pub solid: bool,
+}
+
+impl Collision {
+    pub const fn solid(width: PosType, height: PosType) -> Self {
+        Self { width, height, solid: true }}
+
+    #[allow(dead_code)]
+    pub const fn passable(width: PosType, height: PosType) -> Self {
+        Self { width, height, solid: false }}
+}
+
+// An item that is better than bare hands at breaking and hitting things.
+#[derive(Copy, Clone)]
+pub struct Tool {
+    pub tier: u8, // effective blocks break `1 + TIER_SPEED * tier` times as fast
+    pub durability: u16, // uses until it breaks
+    pub damage: HealthAmount,
+    pub effective: &'static [&'static str], // tags of the blocks it's good at breaking
+}
+
 pub struct GameObjectType {
+    pub key: &'static str,
     pub update_fn_id: Option<UpdateFnIdType>,
     pub texture: Option<&'static str>,
     pub class: GameObjectClass,
+    pub collision: Option<Collision>,
+    pub health: Option<HealthAmount>,
+    pub hardness: Option<PosType>,
+    pub drops: &'static [(&'static str, Amount)],
+    pub name: &'static str,
+    pub max_stack: Amount,
+    pub tags: &'static [&'static str],
+    pub tool: Option<Tool>,
+}
+
+impl GameObjectType {
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.contains(&tag)
+    }
+
+    // The block that the item places, if any.
+    pub fn places(&self) -> Option<&'static str> {
+        match self.class {
+            GameObjectClass::Item { places } => places,
+            _ => None,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn is_tool(&self) -> bool {
+        self.tool.is_some()
+    }
 }
 
 pub struct GameObjectTypes {
-    types: Box<[GameObjectType]>,        // indexed by GameObjectTypeId
-    init_fns: Box<[Option<InitFnType>]>, // indexed by GameObjectTypeId
+    types: Box<[GameObjectType]>,          // indexed by GameObjectTypeId
+    init_fns: Box<[Option<InitFnType>]>,   // indexed by GameObjectTypeId
+    death_fns: Box<[Option<DeathFnType>]>, // indexed by GameObjectTypeId
+    event_fns: Box<[Option<EventFnType>]>, // indexed by GameObjectTypeId
     update_fns: Box<[UpdateFnType]>,     // indexed by UpdateFnIdType
     key_id_map: HashMap<&'static str, GameObjectTypeId>,
 }
@@ -95,6 +288,8 @@ impl GameObjectTypes {
         let mut types = Vec::new();
         let mut update_fns = Vec::new();
         let mut init_fns = Vec::new();
+        let mut death_fns = Vec::new();
+        let mut event_fns = Vec::new();
         let mut key_id_map = HashMap::new();
 
         for builder in TYPES {
@@ -109,6 +304,8 @@ impl GameObjectTypes {
                 "too many game objects for id type");
             key_id_map.insert(builder.key, id);
             init_fns.push(builder.init);
+            death_fns.push(builder.on_death);
+            event_fns.push(builder.on_event);
 
             let update_fn_id = if let Some(update_fn) = builder.update {
                 Some({
@@ -127,17 +324,44 @@ impl GameObjectTypes {
             };
 
             let gameobjtype = GameObjectType {
+                key: builder.key,
                 update_fn_id,
                 texture: builder.texture,
                 class,
+                collision: builder.collision,
+                health: builder.health,
+                hardness: builder.hardness,
+                drops: builder.drops.unwrap_or(&[]),
+                name: builder.name.unwrap_or(builder.key),
+                // tools wear out one by one, so they don't stack
+                max_stack: builder.max_stack
+                    .unwrap_or(if builder.tool.is_some() { 1 } else { DEFAULT_MAX_STACK }),
+                tags: builder.tags.unwrap_or(&[]),
+                tool: builder.tool,
             };
 
             types.push(gameobjtype);
         }
 
+        for gameobjtype in &types {
+            for (key, _) in gameobjtype.drops {
+                assert!(key_id_map.contains_key(key),
+                    "type '{}' drops unknown type '{}'", gameobjtype.key, key);
+            }
+
+            if let Some(key) = gameobjtype.places() {
+                assert!(key_id_map.contains_key(key),
+                    "type '{}' places unknown type '{}'", gameobjtype.key, key);
+            }
+
+            assert!(gameobjtype.max_stack > 0, "type '{}' can't be stacked", gameobjtype.key);
+        }
+
         Self{
             types: types.into(),
             init_fns: init_fns.into(),
+            death_fns: death_fns.into(),
+            event_fns: event_fns.into(),
             update_fns: update_fns.into(),
             key_id_map,
         }
@@ -152,6 +376,38 @@ impl GameObjectTypes {
             &format!("invalid key '{}'", key).to_string())
     }
 
+    pub fn contains(&self, key: &str) -> bool {
+        self.key_id_map.contains_key(key)
+    }
+
+    // Unknown items stack like any other.
+    pub fn max_stack(&self, key: &str) -> Amount {
+        self.key_id_map.get(key)
+            .map(|id| self.types[*id as usize].max_stack)
+            .unwrap_or(DEFAULT_MAX_STACK)
+    }
+
+    // The name shown for an item, which might not be a known type.
+    pub fn name<'k>(&self, key: &'k str) -> &'k str {
+        self.key_id_map.get(key)
+            .map(|id| self.types[*id as usize].name)
+            .unwrap_or(key)
+    }
+
+    // Unknown items are drawn with the error texture.
+    pub fn texture(&self, key: &str) -> Option<&'static str> {
+        match self.key_id_map.get(key) {
+            Some(id) => self.types[*id as usize].texture,
+            None => Some("error"),
+        }
+    }
+
+    // Unknown items have no tags.
+    pub fn has_tag(&self, key: &str, tag: &str) -> bool {
+        self.key_id_map.get(key)
+            .is_some_and(|id| self.types[*id as usize].has_tag(tag))
+    }
+
     pub fn init_entity
             <'a>(&self, entity_builder: &'a mut EntityBuilder, key: &'static str)
             -> Result<()> {
@@ -164,6 +420,11 @@ impl GameObjectTypes {
 
         entity_builder.add(GameObjectTypeComponent{id});
 
+        if let Some(max) = self.from_id(id).health
+                && !entity_builder.has::<Health>() {
+            entity_builder.add(Health::new(max));
+        }
+
         if let Some(id) =
                 self.types.get(
                     *self.key_id_map.get(&key).expect("invalid key") as usize
@@ -178,4 +439,12 @@ impl GameObjectTypes {
     pub fn get_update_fn_from_id(&self, id: UpdateFnIdType) -> UpdateFnType {
         self.update_fns[id as usize]
     }
+
+    pub fn get_death_fn(&self, id: GameObjectTypeId) -> Option<DeathFnType> {
+        self.death_fns[id as usize]
+    }
+
+    pub fn get_event_fn(&self, id: GameObjectTypeId) -> Option<EventFnType> {
+        self.event_fns[id as usize]
+    }
 }
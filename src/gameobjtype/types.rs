@@ -3,14 +3,52 @@
 
 use crate::gameobjtype::GameObjectTypeBuilder;
 
-pub mod tree;
-pub mod test_tile;
-pub mod test;
+pub mod boulder;
+pub mod chest;
+pub mod chest_item;
+pub mod dirt;
+pub mod grass;
+pub mod item;
+pub mod mound;
 pub mod player;
+pub mod rock;
+pub mod sand;
+pub mod snow;
+pub mod stone;
+pub mod test;
+pub mod tree;
+pub mod water;
+pub mod wood;
+pub mod wood_wall;
+pub mod wooden_axe;
+pub mod wooden_pickaxe;
+pub mod wooden_shovel;
+pub mod wooden_sword;
+pub mod workbench;
+pub mod workbench_item;
 
-pub const TYPES: [GameObjectTypeBuilder; 4] = [
-    tree::TYPE,
-    test_tile::TYPE,
-    test::TYPE,
+pub const TYPES: [GameObjectTypeBuilder; 23] = [
+    boulder::TYPE,
+    chest::TYPE,
+    chest_item::TYPE,
+    dirt::TYPE,
+    grass::TYPE,
+    item::TYPE,
+    mound::TYPE,
     player::TYPE,
+    rock::TYPE,
+    sand::TYPE,
+    snow::TYPE,
+    stone::TYPE,
+    test::TYPE,
+    tree::TYPE,
+    water::TYPE,
+    wood::TYPE,
+    wood_wall::TYPE,
+    wooden_axe::TYPE,
+    wooden_pickaxe::TYPE,
+    wooden_shovel::TYPE,
+    wooden_sword::TYPE,
+    workbench::TYPE,
+    workbench_item::TYPE,
 ];
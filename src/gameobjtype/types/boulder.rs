@@ -0,0 +1,12 @@
+use crate::gameobjtype::base::*;
+
+pub const TYPE: GameObjectTypeBuilder = 
+    *GameObjectTypeBuilder::new("boulder")
+    .class(GameObjectClass::block())
+    .texture("boulder")
+    .name("Boulder")
+    .collision(Collision::solid(0.9, 0.9))
+    .tags(&["stone"])
+    .hardness(3.0)
+    .drops(&[("rock", 2)])
+;
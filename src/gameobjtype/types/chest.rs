@@ -0,0 +1,24 @@
+use crate::gameobjtype::base::*;
+
+const SLOTS: usize = 10;
+
+// Keeps whatever inventory it was spawned or loaded with.
+fn init<'a>(entity: &'a mut EntityBuilder) -> Result<&'a mut EntityBuilder> {
+    if !entity.has::<Inventory>() {
+        entity.add(Inventory::new(SLOTS));
+    }
+
+    Ok(entity)
+}
+
+pub const TYPE: GameObjectTypeBuilder = 
+    *GameObjectTypeBuilder::new("chest")
+    .class(GameObjectClass::block())
+    .init(init)
+    .texture("chest")
+    .name("Chest")
+    .tags(&["container", "wood"])
+    .collision(Collision::solid(1.0, 1.0))
+    .hardness(1.5)
+    .drops(&[("chest_item", 1)])
+;
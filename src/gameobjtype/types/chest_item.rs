@@ -0,0 +1,9 @@
+use crate::gameobjtype::base::*;
+
+pub const TYPE: GameObjectTypeBuilder = 
+    *GameObjectTypeBuilder::new("chest_item")
+    .class(GameObjectClass::placeable("chest"))
+    .texture("chest")
+    .name("Chest")
+    .max_stack(16)
+;
@@ -10,7 +10,7 @@ fn init<'a>(entity: &'a mut EntityBuilder) -> Result<&'a mut EntityBuilder> {
 }
 
 pub const TYPE: GameObjectTypeBuilder = 
-    *GameObjectTypeBuilder::new("test_tile")
+    *GameObjectTypeBuilder::new("grass")
     .class(GameObjectClass::block())
     .init(init)
     .texture("grass")
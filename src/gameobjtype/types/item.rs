@@ -0,0 +1,18 @@
+use crate::gameobjtype::base::*;
+
+// An item lying in the world, drawn with the texture of the item in its
+// `DroppedItem`.
+fn init<'a>(entity: &'a mut EntityBuilder) -> Result<&'a mut EntityBuilder> {
+    entity
+        .add(TextureTransform::new()
+            .scale(0.5))
+    ;
+
+    Ok(entity)
+}
+
+pub const TYPE: GameObjectTypeBuilder = 
+    *GameObjectTypeBuilder::new("item")
+    .class(GameObjectClass::entity())
+    .init(init)
+;
@@ -0,0 +1,13 @@
+use crate::gameobjtype::base::*;
+
+pub const TYPE: GameObjectTypeBuilder = 
+    *GameObjectTypeBuilder::new("mound")
+    .class(GameObjectClass::block())
+    .texture("mound")
+    .name("Dirt mound")
+    .tags(&["soil"])
+    .hardness(1.5)
+    // dirt only exists as ground, not as an item, so digging up a mound
+    // leaves the stone buried in it (see its texture)
+    .drops(&[("rock", 1)])
+;
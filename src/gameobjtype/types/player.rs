@@ -1,17 +1,32 @@
 use crate::gameobjtype::base::*;
 
 fn init<'a>(entity: &'a mut EntityBuilder) -> Result<&'a mut EntityBuilder> {
+    // keep the position it was spawned at, if any
+    if !entity.has::<Position>() {
+        entity.add(Position::free(0.0, 0.0));
+    }
+
     entity
         .add(Player::new())
-        .add(Position::free(0.0, 0.0))
+        .add(Velocity::default())
     ;
 
     Ok(entity)
 }
 
+// Respawns at the origin.
+fn on_death(ecs: &mut ECSWorld, id: ECSEntityId, _damage: &Damage) -> Result<()> {
+    ecs.get::<&mut Health>(id)?.heal_fully();
+    *ecs.get::<&mut Position>(id)? = Position::free(0.0, 0.0);
+    Ok(())
+}
+
 pub const TYPE: GameObjectTypeBuilder = 
     *GameObjectTypeBuilder::new("player")
     .class(GameObjectClass::entity())
     .init(init)
     .texture("player")
+    .collision(Collision::solid(0.6, 0.6))
+    .health(10)
+    .on_death(on_death)
 ;
@@ -0,0 +1,9 @@
+use crate::gameobjtype::base::*;
+
+pub const TYPE: GameObjectTypeBuilder = 
+    *GameObjectTypeBuilder::new("rock")
+    .class(GameObjectClass::item())
+    .texture("rock")
+    .name("Rock")
+    .tags(&["stone"])
+;
@@ -1,16 +1,102 @@
 use crate::gameobjtype::base::*;
 
+const FLEE_SPEED: PosType = 6.0; // tiles per second
+const ALARM: &str = "alarm";
+const ALARM_RADIUS: PosType = 6.0;
+
 fn init<'a>(entity: &'a mut EntityBuilder) -> Result<&'a mut EntityBuilder> {
+    // keep the position it was spawned at, if any
+    if !entity.has::<Position>() {
+        entity.add(Position::free(1.0, 1.0));
+    }
+
     entity
-        .add(Position::free(1.0, 1.0))
+        .add(Velocity::default())
     ;
 
     Ok(entity)
 }
 
+// Wanders around, changing direction every two seconds or so, but never
+// heads straight into water.
+fn update(ecs: &mut ECSWorld, id: ECSEntityId, update_data: &mut UpdateData) -> Result<()> {
+    let rng = &mut update_data.rng;
+
+    if rng.random_range(0..(2.0 / update_data.dt) as u32) == 0 {
+        let dx = rng.random_range(-1..=1) as PosType;
+        let dy = rng.random_range(-1..=1) as PosType;
+
+        let ahead = {
+            let pos = ecs.get::<&Position>(id)?;
+            let origin = pos.chunk();
+            Position::tile_containing(&origin, pos.x(&origin) + dx, pos.y(&origin) + dy)
+        };
+        let wet = update_data.tile(&ahead)
+            .is_some_and(|tile| update_data.types.from_id(tile.id).has_tag("liquid"));
+
+        let mut velocity = ecs.get::<&mut Velocity>(id)?;
+        if wet {
+            *velocity = Velocity::default();
+        } else {
+            velocity.x = dx * 3.0;
+            velocity.y = dy * 3.0;
+        }
+    }
+
+    Ok(())
+}
+
+// Runs away from whatever hit it, and warns the others nearby, which run
+// away as well.
+fn on_event(
+        ecs: &mut ECSWorld,
+        id: ECSEntityId,
+        event: &GameEvent,
+        update_data: &mut UpdateData) -> Result<()> {
+
+    let source = match event {
+        GameEvent::EntityDamaged { target, source: Some(source), .. } if *target == id => {
+            update_data.emit(GameEvent::Custom {
+                key: ALARM,
+                source: id,
+                target: Some(*source),
+            });
+            *source
+        },
+        GameEvent::Custom { key: ALARM, source: warner, target: Some(source) }
+                if *warner != id && distance(ecs, id, *warner)? <= ALARM_RADIUS => *source,
+        _ => return Ok(()),
+    };
+
+    let (dx, dy) = offset(ecs, source, id)?;
+    let distance = (dx * dx + dy * dy).sqrt().max(0.01);
+
+    let mut velocity = ecs.get::<&mut Velocity>(id)?;
+    velocity.x = dx / distance * FLEE_SPEED;
+    velocity.y = dy / distance * FLEE_SPEED;
+
+    Ok(())
+}
+
+fn offset(ecs: &ECSWorld, from: ECSEntityId, to: ECSEntityId) -> Result<(PosType, PosType)> {
+    let from = ecs.get::<&Position>(from)?;
+    let to = ecs.get::<&Position>(to)?;
+    let origin = to.chunk();
+    Ok((to.x(&origin) - from.x(&origin), to.y(&origin) - from.y(&origin)))
+}
+
+fn distance(ecs: &ECSWorld, a: ECSEntityId, b: ECSEntityId) -> Result<PosType> {
+    let (dx, dy) = offset(ecs, a, b)?;
+    Ok((dx * dx + dy * dy).sqrt())
+}
+
 pub const TYPE: GameObjectTypeBuilder = 
     *GameObjectTypeBuilder::new("test")
     .class(GameObjectClass::entity())
     .init(init)
+    .update(update)
+    .on_event(on_event)
     .texture("error")
+    .collision(Collision::solid(0.8, 0.8))
+    .health(5)
 ;
@@ -16,4 +16,8 @@ pub const TYPE: GameObjectTypeBuilder =
     .class(GameObjectClass::block())
     .init(init)
     .texture("tree")
+    .collision(Collision::solid(0.8, 0.8))
+    .tags(&["wood"])
+    .hardness(1.5)
+    .drops(&[("wood", 2)])
 ;
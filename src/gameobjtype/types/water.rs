@@ -0,0 +1,18 @@
+use crate::gameobjtype::base::*;
+
+fn init<'a>(entity: &'a mut EntityBuilder) -> Result<&'a mut EntityBuilder> {
+    entity
+        .add(TextureTransform::new()
+            .random_direction())
+    ;
+
+    Ok(entity)
+}
+
+pub const TYPE: GameObjectTypeBuilder = 
+    *GameObjectTypeBuilder::new("water")
+    .class(GameObjectClass::block())
+    .init(init)
+    .texture("water")
+    .tags(&["liquid"])
+;
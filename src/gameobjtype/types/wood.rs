@@ -0,0 +1,10 @@
+use crate::gameobjtype::base::*;
+
+pub const TYPE: GameObjectTypeBuilder = 
+    *GameObjectTypeBuilder::new("wood")
+    .class(GameObjectClass::placeable("wood_wall"))
+    .texture("wood")
+    .name("Wood")
+    .max_stack(64)
+    .tags(&["wood", "fuel"])
+;
@@ -0,0 +1,12 @@
+use crate::gameobjtype::base::*;
+
+pub const TYPE: GameObjectTypeBuilder = 
+    *GameObjectTypeBuilder::new("wood_wall")
+    .class(GameObjectClass::block())
+    .texture("wood_wall")
+    .name("Wooden wall")
+    .collision(Collision::solid(1.0, 1.0))
+    .tags(&["wood"])
+    .hardness(1.0)
+    .drops(&[("wood", 1)])
+;
@@ -0,0 +1,14 @@
+use crate::gameobjtype::base::*;
+
+pub const TYPE: GameObjectTypeBuilder = 
+    *GameObjectTypeBuilder::new("wooden_axe")
+    .class(GameObjectClass::item())
+    .texture("wooden_axe")
+    .name("Wooden axe")
+    .tool(Tool {
+        tier: 1,
+        durability: 60,
+        damage: 2,
+        effective: &["wood"],
+    })
+;
@@ -0,0 +1,14 @@
+use crate::gameobjtype::base::*;
+
+pub const TYPE: GameObjectTypeBuilder = 
+    *GameObjectTypeBuilder::new("wooden_shovel")
+    .class(GameObjectClass::item())
+    .texture("wooden_shovel")
+    .name("Wooden shovel")
+    .tool(Tool {
+        tier: 1,
+        durability: 60,
+        damage: 1,
+        effective: &["soil"],
+    })
+;
@@ -0,0 +1,14 @@
+use crate::gameobjtype::base::*;
+
+pub const TYPE: GameObjectTypeBuilder = 
+    *GameObjectTypeBuilder::new("wooden_sword")
+    .class(GameObjectClass::item())
+    .texture("wooden_sword")
+    .name("Wooden sword")
+    .tool(Tool {
+        tier: 1,
+        durability: 60,
+        damage: 3,
+        effective: &[],
+    })
+;
@@ -0,0 +1,12 @@
+use crate::gameobjtype::base::*;
+
+pub const TYPE: GameObjectTypeBuilder = 
+    *GameObjectTypeBuilder::new("workbench")
+    .class(GameObjectClass::block())
+    .texture("workbench")
+    .name("Workbench")
+    .collision(Collision::solid(1.0, 1.0))
+    .tags(&["wood"])
+    .hardness(1.5)
+    .drops(&[("workbench_item", 1)])
+;
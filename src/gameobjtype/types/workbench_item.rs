@@ -0,0 +1,9 @@
+use crate::gameobjtype::base::*;
+
+pub const TYPE: GameObjectTypeBuilder = 
+    *GameObjectTypeBuilder::new("workbench_item")
+    .class(GameObjectClass::placeable("workbench"))
+    .texture("workbench")
+    .name("Workbench")
+    .max_stack(16)
+;
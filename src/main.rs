@@ -1,5 +1,8 @@
 mod ui;
 mod game;
+mod save;
+mod chunk;
+mod spatial;
 mod debug;
 mod types;
 mod utils;
@@ -9,8 +12,11 @@ mod random;
 mod prelude;
 mod textures;
 mod constants;
+mod worldgen;
+mod crafting;
 mod components;
 mod gameobjtype;
+mod gameevent;
 
 use game::Game;
 use prelude::*;
@@ -18,9 +24,25 @@ use constants::*;
 
 use sdl2::pixels::Color;
 
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use std::path::Path;
+
+// Ticks that are further behind than this are skipped, so that a long lag
+// doesn't make the game run fast until it has caught up.
+const MAX_TICKS_PER_FRAME: u32 = 5;
 
 fn run() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let save_dir = Path::new(SAVE_DIR);
+
+    // prints what was recorded with --record-events, instead of playing
+    if args.iter().any(|arg| arg == "--print-events") {
+        for event in save::read_events(save_dir).context("reading event log")? {
+            println!("{:?}", event);
+        }
+        return Ok(())
+    }
+
     let sdl_context = sdl2::init().unwrap();
     let video_subsystem = sdl_context.video().unwrap();
 
@@ -42,19 +64,53 @@ fn run() -> Result<()> {
     let mut game = Game::new();
     game.init_textures(&texture_creator).context("loading textures")?;
     game.init_fonts(&ttf_context, &texture_creator).context("loading fonts")?;
-    game.init().context("initializing")?;
+    game.init_biomes().context("loading biomes")?;
+    game.init_structures().context("loading structures")?;
+    game.init_recipes().context("loading recipes")?;
+
+    if save::exists(save_dir) {
+        game.load(save_dir).context("loading world")?;
+    } else {
+        game.init().context("initializing")?;
+    }
+
+    if args.iter().any(|arg| arg == "--record-events") {
+        game.record_events(save_dir);
+    }
+
+    let tick = Duration::from_secs(1) / TPS as u32;
+    let mut behind = Duration::ZERO;
+    let mut last_frame = Instant::now();
 
     'main: loop {
         let timer = debug::Timer::new("WHOLE FRAME");
 
-        if game.update(&mut event_pump).context("updating")? {
+        if game.handle_events(&mut event_pump) {
             break 'main;
         }
 
+        // the world runs at a fixed rate, however often frames are drawn
+        let now = Instant::now();
+        behind += now - last_frame;
+        last_frame = now;
+
+        let mut ticks = 0;
+        while behind >= tick {
+            if ticks == MAX_TICKS_PER_FRAME {
+                behind = Duration::ZERO;
+                break
+            }
+
+            game.tick().context("updating")?;
+            behind -= tick;
+            ticks += 1;
+        }
+
         canvas.set_draw_color(Color::RGB(0, 0, 0));
         canvas.clear();
 
-        game.render(&mut canvas).context("rendering")?;
+        game.render(&mut canvas, behind.as_secs_f32() / tick.as_secs_f32())
+            .context("rendering")?;
 
         canvas.present();
 
@@ -72,6 +128,8 @@ fn run() -> Result<()> {
             (1000 / FPS).saturating_sub(elapsed)));
     }
 
+    game.save(save_dir).context("saving world")?;
+
     println!();
 
     Ok(())
@@ -0,0 +1,255 @@
+pub mod region;
+
+use crate::prelude::*;
+use crate::gameobjtype::GameObjectTypes;
+use crate::components::*;
+use crate::chunk::{Chunk, Tile};
+use crate::utils::deserialize_interned;
+use crate::constants::CHUNK_SIZE;
+use crate::gameevent::RecordedEvent;
+
+use hecs::World as ECSWorld;
+use hecs::Entity as EntityId;
+use hecs::EntityBuilder;
+
+use serde::{Serialize, Deserialize};
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+pub const SAVE_VERSION: u32 = 4;
+const WORLD_FILE: &str = "world.json";
+const EVENTS_FILE: &str = "events.log";
+
+// An entity outside of the ECS. Components that aren't stored here are
+// recreated by the type's init function when the entity is restored.
+#[derive(Serialize, Deserialize)]
+pub struct SavedEntity {
+    #[serde(deserialize_with = "deserialize_interned")]
+    pub key: Key,
+    pub position: Option<Position>,
+    pub texture_transform: Option<TextureTransform>,
+    pub inventory: Option<Inventory>,
+    #[serde(default)]
+    pub health: Option<Health>,
+    #[serde(default)]
+    pub dropped_item: Option<DroppedItem>,
+    #[serde(default)]
+    pub uid: Option<Uid>,
+    #[serde(default)]
+    pub player: Option<SavedPlayer>,
+}
+
+// The part of the player component that outlasts a session.
+#[derive(Serialize, Deserialize)]
+pub struct SavedPlayer {
+    pub selected_slot: usize,
+}
+
+// Tiles are stored by key, through a palette of the keys used in the chunk.
+#[derive(Serialize, Deserialize)]
+pub struct SavedChunk {
+    #[serde(deserialize_with = "deserialize_interned_vec")]
+    pub palette: Vec<Key>,
+    pub tiles: Vec<(u8, u8)>, // (index into palette, state)
+    pub entities: Vec<SavedEntity>,
+}
+
+fn deserialize_interned_vec
+        <'de, D: serde::Deserializer<'de>>(deserializer: D)
+        -> std::result::Result<Vec<Key>, D::Error> {
+
+    let keys = Vec::<String>::deserialize(deserializer)?;
+    Ok(keys.iter().map(|key| crate::utils::intern(key)).collect())
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SavedWorld {
+    pub version: u32,
+    pub seed: u64,
+    #[serde(default)]
+    pub tick: u64,
+    #[serde(default)]
+    pub next_uid: u64,
+    pub player: SavedEntity,
+}
+
+impl SavedEntity {
+    pub fn new(ecs: &ECSWorld, types: &GameObjectTypes, id: EntityId) -> Result<Self> {
+        let type_id = ecs.get::<&GameObjectTypeComponent>(id)?.id;
+
+        Ok(Self {
+            key: types.from_id(type_id).key,
+            position: ecs.get::<&Position>(id).ok().map(|c| (*c).clone()),
+            texture_transform: ecs.get::<&TextureTransform>(id).ok().map(|c| (*c).clone()),
+            inventory: ecs.get::<&Inventory>(id).ok().map(|c| (*c).clone()),
+            health: ecs.get::<&Health>(id).ok().map(|c| (*c).clone()),
+            dropped_item: ecs.get::<&DroppedItem>(id).ok().map(|c| (*c).clone()),
+            uid: ecs.get::<&Uid>(id).ok().map(|c| *c),
+            player: ecs.get::<&Player>(id).ok().map(|c| SavedPlayer {
+                selected_slot: c.selected_slot,
+            }),
+        })
+    }
+
+    // Like `new`, but also removes the entity from the world.
+    pub fn take(ecs: &mut ECSWorld, types: &GameObjectTypes, id: EntityId) -> Result<Self> {
+        let saved = Self::new(ecs, types, id)?;
+        ecs.despawn(id)?;
+        Ok(saved)
+    }
+
+    pub fn restore(self, ecs: &mut ECSWorld, types: &GameObjectTypes) -> Result<EntityId> {
+        if !types.contains(self.key) {
+            bail!("unknown type '{}'", self.key);
+        }
+        if let Some(dropped) = &self.dropped_item
+                && !types.contains(dropped.item.key) {
+            bail!("unknown item '{}'", dropped.item.key);
+        }
+
+        let mut builder = EntityBuilder::new();
+
+        // init functions may expect a position
+        if let Some(position) = &self.position {
+            builder.add(position.clone());
+        }
+
+        types.init_entity(&mut builder, self.key)?;
+
+        // override whatever init added with the saved state
+        if let Some(position) = self.position {
+            builder.add(position);
+        }
+        if let Some(texture_transform) = self.texture_transform {
+            builder.add(texture_transform);
+        }
+        if let Some(inventory) = self.inventory {
+            builder.add(inventory);
+        }
+        if let Some(health) = self.health {
+            builder.add(health);
+        }
+        if let Some(dropped_item) = self.dropped_item {
+            builder.add(dropped_item);
+        }
+        if let Some(uid) = self.uid {
+            builder.add(uid);
+        }
+        if let Some(saved) = self.player
+                && let Some(player) = builder.get_mut::<&mut Player>() {
+            player.selected_slot = saved.selected_slot;
+        }
+
+        Ok(ecs.spawn(builder.build()))
+    }
+}
+
+impl SavedChunk {
+    pub fn new(chunk: &Chunk, types: &GameObjectTypes, entities: Vec<SavedEntity>) -> Self {
+        let mut palette = Vec::new();
+        let mut tiles = Vec::with_capacity(CHUNK_SIZE * CHUNK_SIZE);
+
+        for (_, _, tile) in chunk.iter() {
+            let key = types.from_id(tile.id).key;
+            let index = palette.iter().position(|k| *k == key).unwrap_or_else(|| {
+                palette.push(key);
+                palette.len() - 1
+            });
+            tiles.push((index as u8, tile.state));
+        }
+
+        Self {
+            palette,
+            tiles,
+            entities,
+        }
+    }
+
+    pub fn restore_tiles(&self, types: &GameObjectTypes) -> Result<Chunk> {
+        if self.tiles.len() != CHUNK_SIZE * CHUNK_SIZE {
+            bail!("wrong amount of tiles");
+        }
+
+        let mut ids = Vec::with_capacity(self.palette.len());
+        for key in &self.palette {
+            if !types.contains(key) {
+                bail!("unknown tile type '{}'", key);
+            }
+            ids.push(types.get_id(key));
+        }
+
+        let tiles = self.tiles.iter().map(|(index, state)| {
+            Ok(Tile {
+                id: *ids.get(*index as usize).ok_or_else(|| anyhow!("invalid palette index"))?,
+                state: *state,
+            })
+        }).collect::<Result<_>>()?;
+
+        Ok(Chunk::new(tiles))
+    }
+}
+
+pub fn exists(dir: &Path) -> bool {
+    dir.join(WORLD_FILE).is_file()
+}
+
+pub fn write_world(dir: &Path, world: &SavedWorld) -> Result<()> {
+    fs::create_dir_all(dir).context("creating save directory")?;
+
+    // write to a temporary file first, so a crash never leaves a half
+    // written save behind
+    let path = dir.join(WORLD_FILE);
+    let tmp_path = path.with_extension("tmp");
+
+    let mut writer = BufWriter::new(File::create(&tmp_path)?);
+    serde_json::to_writer(&mut writer, world)?;
+    writer.flush()?;
+    drop(writer);
+
+    fs::rename(&tmp_path, &path)?;
+
+    Ok(())
+}
+
+pub fn read_world(dir: &Path) -> Result<SavedWorld> {
+    let reader = BufReader::new(File::open(dir.join(WORLD_FILE))?);
+    let world: SavedWorld = serde_json::from_reader(reader)?;
+
+    if world.version != SAVE_VERSION {
+        bail!("unsupported save version {} (expected {})", world.version, SAVE_VERSION);
+    }
+
+    Ok(world)
+}
+
+// Adds recorded events to the end of the event log, one json object per
+// line.
+pub fn append_events(dir: &Path, events: &[RecordedEvent]) -> Result<()> {
+    fs::create_dir_all(dir).context("creating save directory")?;
+
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(EVENTS_FILE))?;
+    let mut writer = BufWriter::new(file);
+
+    for event in events {
+        serde_json::to_writer(&mut writer, event)?;
+        writeln!(writer)?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+pub fn read_events(dir: &Path) -> Result<Vec<RecordedEvent>> {
+    let reader = BufReader::new(File::open(dir.join(EVENTS_FILE))?);
+
+    reader.lines().enumerate()
+        .map(|(i, line)| {
+            serde_json::from_str(&line?).with_context(|| format!("reading event on line {}", i + 1))
+        })
+        .collect()
+}
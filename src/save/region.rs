@@ -0,0 +1,367 @@
+// Chunks are stored in region files of REGION_SIZE x REGION_SIZE chunks.
+//
+// layout:
+//   magic        4 bytes
+//   entry table  REGION_CHUNKS * (offset: u32, length: u32, crc32: u32)
+//   chunk data   deflate compressed json, at the offsets in the table
+//
+// An entry with length 0 is an empty slot. All numbers are little endian.
+
+use crate::prelude::*;
+use crate::components::ChunkPos;
+use super::SavedChunk;
+
+use flate2::Crc;
+use flate2::Compression;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+const REGION_SIZE: ChunkPosType = 32;
+const REGION_CHUNKS: usize = (REGION_SIZE * REGION_SIZE) as usize;
+const MAGIC: &[u8; 4] = b"MFRG";
+const ENTRY_LEN: usize = 12;
+const HEADER_LEN: usize = MAGIC.len() + REGION_CHUNKS * ENTRY_LEN;
+const REGION_DIR: &str = "regions";
+
+#[derive(Clone, Copy)]
+struct Entry {
+    offset: u32,
+    length: u32,
+    checksum: u32,
+}
+
+fn region_of(pos: &ChunkPos) -> (ChunkPosType, ChunkPosType) {
+    (pos.x.div_euclid(REGION_SIZE), pos.y.div_euclid(REGION_SIZE))
+}
+
+fn index_of(pos: &ChunkPos) -> usize {
+    (pos.y.rem_euclid(REGION_SIZE) * REGION_SIZE + pos.x.rem_euclid(REGION_SIZE)) as usize
+}
+
+fn region_path(dir: &Path, region: (ChunkPosType, ChunkPosType)) -> PathBuf {
+    dir.join(REGION_DIR).join(format!("r.{}.{}.bin", region.0, region.1))
+}
+
+fn checksum(data: &[u8]) -> u32 {
+    let mut crc = Crc::new();
+    crc.update(data);
+    crc.sum()
+}
+
+fn compress(chunk: &SavedChunk) -> Result<Vec<u8>> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    serde_json::to_writer(&mut encoder, chunk)?;
+    Ok(encoder.finish()?)
+}
+
+fn decompress(data: &[u8]) -> Result<SavedChunk> {
+    Ok(serde_json::from_reader(DeflateDecoder::new(data))?)
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes(bytes.try_into().unwrap())
+}
+
+// Reads the entry table, and whether the header is intact. A damaged header
+// is read as far as it goes, since every entry is checked against its
+// checksum anyway, so only the chunks that are damaged themselves get lost.
+fn read_table(file: &mut File) -> Result<(Box<[Entry]>, bool)> {
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    Read::by_ref(file).take(HEADER_LEN as u64).read_to_end(&mut header)
+        .context("reading region header")?;
+
+    let intact = header.len() == HEADER_LEN && header.starts_with(MAGIC);
+
+    let mut table: Vec<Entry> = header.get(MAGIC.len()..).unwrap_or_default()
+        .chunks_exact(ENTRY_LEN)
+        .map(|entry| Entry {
+            offset:   read_u32(&entry[0..4]),
+            length:   read_u32(&entry[4..8]),
+            checksum: read_u32(&entry[8..12]),
+        })
+        .collect();
+    table.resize(REGION_CHUNKS, Entry { offset: 0, length: 0, checksum: 0 });
+
+    Ok((table.into_boxed_slice(), intact))
+}
+
+// Returns the compressed data of the entry, if the slot is used.
+fn read_entry(file: &mut File, entry: Entry) -> Result<Option<Vec<u8>>> {
+    if entry.length == 0 {
+        return Ok(None)
+    }
+
+    // a damaged entry could point anywhere
+    let end = entry.offset as u64 + entry.length as u64;
+    if (entry.offset as usize) < HEADER_LEN || end > file.metadata()?.len() {
+        bail!("chunk data out of bounds");
+    }
+
+    let mut data = vec![0; entry.length as usize];
+    file.seek(SeekFrom::Start(entry.offset as u64))?;
+    file.read_exact(&mut data).context("reading chunk data")?;
+
+    if checksum(&data) != entry.checksum {
+        bail!("checksum mismatch");
+    }
+
+    Ok(Some(data))
+}
+
+pub fn read_chunk(dir: &Path, pos: &ChunkPos) -> Result<Option<SavedChunk>> {
+    let path = region_path(dir, region_of(pos));
+    if !path.is_file() {
+        return Ok(None)
+    }
+
+    let mut file = File::open(&path)?;
+    let (table, _) = read_table(&mut file)?;
+
+    if let Some(data) = read_entry(&mut file, table[index_of(pos)])? {
+        Ok(Some(decompress(&data)?))
+    } else {
+        Ok(None)
+    }
+}
+
+// Reads every valid entry of an existing region file. Entries that can't be
+// read are dropped, so that the region gets rewritten without the damaged
+// parts. A copy of the file is kept aside first, so nothing is lost for good.
+fn read_region(path: &Path) -> Result<HashMap<usize, Vec<u8>>> {
+    let mut entries = HashMap::new();
+
+    if !path.is_file() {
+        return Ok(entries)
+    }
+
+    let mut file = File::open(path)?;
+    let (table, intact) = read_table(&mut file)?;
+    let mut damaged = !intact;
+
+    if !intact {
+        eprintln!("error: {}: damaged header, recovering what's left", path.display());
+    }
+
+    for (index, entry) in table.iter().enumerate() {
+        match read_entry(&mut file, *entry) {
+            Ok(Some(data)) => { entries.insert(index, data); },
+            Ok(None) => {},
+            Err(err) => {
+                eprintln!("error: {}: dropping chunk {}: {}", path.display(), index, err);
+                damaged = true;
+            },
+        }
+    }
+
+    if damaged {
+        let backup = back_up(path)?;
+        eprintln!("error: {}: kept a copy at {}", path.display(), backup.display());
+    }
+
+    Ok(entries)
+}
+
+// Copies a damaged region file aside, without replacing earlier copies.
+fn back_up(path: &Path) -> Result<PathBuf> {
+    let mut backup = path.with_extension("corrupt");
+    let mut n = 1;
+    while backup.exists() {
+        backup = path.with_extension(format!("corrupt{}", n));
+        n += 1;
+    }
+
+    fs::copy(path, &backup)
+        .with_context(|| format!("backing up {}", path.display()))?;
+
+    Ok(backup)
+}
+
+fn write_region(path: &Path, entries: &HashMap<usize, Vec<u8>>) -> Result<()> {
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    let mut body = Vec::new();
+
+    header.extend_from_slice(MAGIC);
+
+    for index in 0..REGION_CHUNKS {
+        let (offset, length, crc) = if let Some(data) = entries.get(&index) {
+            let offset = HEADER_LEN + body.len();
+            body.extend_from_slice(data);
+            (offset as u32, data.len() as u32, checksum(data))
+        } else {
+            (0, 0, 0)
+        };
+
+        header.extend_from_slice(&offset.to_le_bytes());
+        header.extend_from_slice(&length.to_le_bytes());
+        header.extend_from_slice(&crc.to_le_bytes());
+    }
+
+    let tmp_path = path.with_extension("tmp");
+    let mut file = File::create(&tmp_path)?;
+    file.write_all(&header)?;
+    file.write_all(&body)?;
+    file.sync_all()?;
+    drop(file);
+
+    fs::rename(&tmp_path, path)?;
+
+    Ok(())
+}
+
+pub fn write_chunks<'a>(
+        dir: &Path,
+        chunks: impl IntoIterator<Item = (&'a ChunkPos, &'a SavedChunk)>)
+        -> Result<()> {
+
+    fs::create_dir_all(dir.join(REGION_DIR)).context("creating region directory")?;
+
+    let mut regions: HashMap<(ChunkPosType, ChunkPosType), Vec<_>> = HashMap::new();
+    for (pos, chunk) in chunks {
+        regions.entry(region_of(pos)).or_default().push((pos, chunk));
+    }
+
+    for (region, chunks) in regions {
+        let path = region_path(dir, region);
+        let mut entries = read_region(&path)?;
+
+        for (pos, chunk) in chunks {
+            entries.insert(index_of(pos), compress(chunk)?);
+        }
+
+        write_region(&path, &entries)
+            .with_context(|| format!("writing {}", path.display()))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join(format!("crate-region-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn chunk(ground: Key) -> SavedChunk {
+        SavedChunk {
+            palette: vec![ground],
+            tiles: vec![(0, 0); 4],
+            entities: Vec::new(),
+        }
+    }
+
+    fn write(dir: &Path, chunks: &[(ChunkPos, Key)]) {
+        let chunks: Vec<_> = chunks.iter().map(|(pos, key)| (pos.clone(), chunk(key))).collect();
+        write_chunks(dir, chunks.iter().map(|(pos, chunk)| (pos, chunk))).unwrap();
+    }
+
+    fn ground(dir: &Path, pos: &ChunkPos) -> Option<Key> {
+        read_chunk(dir, pos).unwrap().map(|chunk| chunk.palette[0])
+    }
+
+    fn backups(dir: &Path) -> usize {
+        fs::read_dir(dir.join(REGION_DIR)).unwrap()
+            .filter(|entry| entry.as_ref().unwrap().path().to_string_lossy().contains("corrupt"))
+            .count()
+    }
+
+    #[test]
+    fn negative_positions() {
+        assert_eq!(region_of(&ChunkPos::new(-1, -1)), (-1, -1));
+        assert_eq!(index_of(&ChunkPos::new(-1, -1)), REGION_CHUNKS - 1);
+        assert_eq!(region_of(&ChunkPos::new(-32, -33)), (-1, -2));
+        assert_eq!(index_of(&ChunkPos::new(-32, -33)), 31 * REGION_SIZE as usize);
+        assert_eq!(region_of(&ChunkPos::new(31, 32)), (0, 1));
+        assert_eq!(index_of(&ChunkPos::new(31, 32)), 31);
+    }
+
+    #[test]
+    fn round_trip() {
+        let dir = temp_dir("round-trip");
+        let (a, b, c) = (ChunkPos::new(0, 0), ChunkPos::new(-1, -1), ChunkPos::new(5, -40));
+        write(&dir, &[(a.clone(), "grass"), (b.clone(), "sand")]);
+        write(&dir, &[(c.clone(), "water"), (a.clone(), "stone")]);
+
+        assert_eq!(ground(&dir, &a), Some("stone"));
+        assert_eq!(ground(&dir, &b), Some("sand"));
+        assert_eq!(ground(&dir, &c), Some("water"));
+        assert_eq!(ground(&dir, &ChunkPos::new(1, 0)), None);
+        assert_eq!(ground(&dir, &ChunkPos::new(100, 100)), None);
+        assert_eq!(backups(&dir), 0);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn checksum_mismatch() {
+        let dir = temp_dir("checksum");
+        let (a, b) = (ChunkPos::new(0, 0), ChunkPos::new(1, 0));
+        write(&dir, &[(a.clone(), "grass"), (b.clone(), "sand")]);
+
+        // flip a byte of the first chunk's data
+        let path = region_path(&dir, (0, 0));
+        let mut bytes = fs::read(&path).unwrap();
+        let offset = read_u32(&bytes[MAGIC.len()..MAGIC.len() + 4]) as usize;
+        bytes[offset] ^= 0xff;
+        fs::write(&path, &bytes).unwrap();
+
+        assert!(read_chunk(&dir, &a).is_err());
+        assert_eq!(ground(&dir, &b), Some("sand"));
+
+        // rewriting the region drops the damaged chunk, but keeps a copy
+        write(&dir, &[(ChunkPos::new(2, 0), "water")]);
+        assert_eq!(ground(&dir, &a), None);
+        assert_eq!(ground(&dir, &b), Some("sand"));
+        assert_eq!(fs::read(path.with_extension("corrupt")).unwrap(), bytes);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn damaged_header() {
+        let dir = temp_dir("header");
+        let pos = ChunkPos::new(3, 3);
+        write(&dir, &[(pos.clone(), "grass")]);
+
+        let path = region_path(&dir, (0, 0));
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[..MAGIC.len()].copy_from_slice(b"XXXX");
+        fs::write(&path, &bytes).unwrap();
+
+        // the entries are still checked against their checksums
+        write(&dir, &[(ChunkPos::new(4, 3), "sand")]);
+        assert_eq!(ground(&dir, &pos), Some("grass"));
+        assert_eq!(backups(&dir), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn truncated_header() {
+        let dir = temp_dir("truncated");
+        let pos = ChunkPos::new(0, 1);
+        write(&dir, &[(pos.clone(), "grass")]);
+
+        let path = region_path(&dir, (0, 0));
+        let bytes = fs::read(&path).unwrap();
+        fs::write(&path, &bytes[..HEADER_LEN / 2]).unwrap();
+
+        assert!(read_chunk(&dir, &pos).is_err());
+
+        write(&dir, &[(ChunkPos::new(0, 2), "sand")]);
+        assert_eq!(ground(&dir, &pos), None);
+        assert_eq!(ground(&dir, &ChunkPos::new(0, 2)), Some("sand"));
+        assert_eq!(fs::read(path.with_extension("corrupt")).unwrap(), &bytes[..HEADER_LEN / 2]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
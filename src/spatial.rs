@@ -0,0 +1,257 @@
+// Finds entities by where they are, without going through every entity.
+// Every entity with a position is listed under its chunk, and free entities
+// are also listed under a finer cell, since they can be anywhere and move.
+// Queries take coordinates relative to an origin chunk, see `Position::x`.
+// Entities are moved in the index by `Game::move_entity`, and refreshed after
+// type functions ran, since those can change positions directly.
+
+use crate::types::*;
+use crate::constants::CHUNK_SIZE;
+use crate::components::{Position, ChunkPos};
+
+use hecs::World as ECSWorld;
+use hecs::Entity as EntityId;
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+const CELL_SIZE: PosType = 4.0;
+const CELLS_PER_CHUNK: ChunkPosType = (CHUNK_SIZE as PosType / CELL_SIZE) as ChunkPosType;
+
+type Cell = (ChunkPosType, ChunkPosType);
+
+struct Entry {
+    chunk: ChunkPos,
+    cell: Option<Cell>, // only set for free entities
+    x: PosType, // offset within the chunk
+    y: PosType,
+}
+
+impl Entry {
+    fn x(&self, origin: &ChunkPos) -> PosType {
+        (self.chunk.x - origin.x) as PosType * CHUNK_SIZE as PosType + self.x
+    }
+
+    fn y(&self, origin: &ChunkPos) -> PosType {
+        (self.chunk.y - origin.y) as PosType * CHUNK_SIZE as PosType + self.y
+    }
+}
+
+pub struct SpatialIndex {
+    entries: HashMap<EntityId, Entry>,
+    chunks: HashMap<ChunkPos, Vec<EntityId>>,
+    cells: HashMap<Cell, Vec<EntityId>>,
+}
+
+// The cell of a point relative to `origin`.
+fn cell_of(origin: &ChunkPos, x: PosType, y: PosType) -> Cell {
+    (
+        origin.x * CELLS_PER_CHUNK + (x / CELL_SIZE).floor() as ChunkPosType,
+        origin.y * CELLS_PER_CHUNK + (y / CELL_SIZE).floor() as ChunkPosType,
+    )
+}
+
+fn pos_cell(pos: &Position) -> Option<Cell> {
+    if pos.is_free() {
+        let (x, y) = pos.local();
+        Some(cell_of(&pos.chunk(), x, y))
+    } else {
+        None
+    }
+}
+
+fn remove_from<K: Hash + Eq>(map: &mut HashMap<K, Vec<EntityId>>, key: &K, id: EntityId) {
+    if let Some(ids) = map.get_mut(key) {
+        if let Some(index) = ids.iter().position(|other| *other == id) {
+            ids.swap_remove(index);
+        }
+        if ids.is_empty() {
+            map.remove(key);
+        }
+    }
+}
+
+impl SpatialIndex {
+    pub fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+            chunks: HashMap::new(),
+            cells: HashMap::new(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.chunks.clear();
+        self.cells.clear();
+    }
+
+    pub fn insert(&mut self, id: EntityId, pos: &Position) {
+        self.remove(id);
+
+        let (x, y) = pos.local();
+        let entry = Entry {
+            chunk: pos.chunk(),
+            cell: pos_cell(pos),
+            x,
+            y,
+        };
+
+        self.chunks.entry(entry.chunk.clone()).or_default().push(id);
+        if let Some(cell) = entry.cell {
+            self.cells.entry(cell).or_default().push(id);
+        }
+
+        self.entries.insert(id, entry);
+    }
+
+    pub fn remove(&mut self, id: EntityId) {
+        if let Some(entry) = self.entries.remove(&id) {
+            remove_from(&mut self.chunks, &entry.chunk, id);
+            if let Some(cell) = entry.cell {
+                remove_from(&mut self.cells, &cell, id);
+            }
+        }
+    }
+
+    pub fn update(&mut self, id: EntityId, pos: &Position) {
+        if let Some(entry) = self.entries.get_mut(&id)
+                && entry.cell == pos_cell(pos)
+                && entry.chunk == pos.chunk() {
+            (entry.x, entry.y) = pos.local();
+            return
+        }
+
+        self.insert(id, pos);
+    }
+
+    // Picks up a position that was changed without going through the index,
+    // or forgets the entity if it is gone.
+    pub fn refresh(&mut self, id: EntityId, ecs: &ECSWorld) {
+        match ecs.get::<&Position>(id) {
+            Ok(pos) => self.update(id, &pos),
+            Err(_) => self.remove(id),
+        }
+    }
+
+    pub fn in_chunk(&self, chunk: &ChunkPos) -> &[EntityId] {
+        self.chunks.get(chunk).map(|ids| ids.as_slice()).unwrap_or(&[])
+    }
+
+    // Entities whose position lies within the rectangle.
+    pub fn in_rect(
+            &self,
+            origin: &ChunkPos,
+            x1: PosType,
+            y1: PosType,
+            x2: PosType,
+            y2: PosType) -> Vec<EntityId> {
+
+        let mut ids = Vec::new();
+        let inside = |entry: &Entry| {
+            let (x, y) = (entry.x(origin), entry.y(origin));
+            x >= x1 && x <= x2 && y >= y1 && y <= y2
+        };
+
+        // tile entities
+        let chunk_of = |x, y| Position::free_in(origin.clone(), x, y).chunk();
+        let (chunk1, chunk2) = (chunk_of(x1, y1), chunk_of(x2, y2));
+        for cx in chunk1.x..=chunk2.x {
+            for cy in chunk1.y..=chunk2.y {
+                for id in self.in_chunk(&ChunkPos::new(cx, cy)) {
+                    let entry = &self.entries[id];
+                    if entry.cell.is_none() && inside(entry) {
+                        ids.push(*id);
+                    }
+                }
+            }
+        }
+
+        // free entities
+        let (cell1, cell2) = (cell_of(origin, x1, y1), cell_of(origin, x2, y2));
+        for cx in cell1.0..=cell2.0 {
+            for cy in cell1.1..=cell2.1 {
+                for id in self.cells.get(&(cx, cy)).into_iter().flatten() {
+                    if inside(&self.entries[id]) {
+                        ids.push(*id);
+                    }
+                }
+            }
+        }
+
+        ids
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(mut ids: Vec<EntityId>) -> Vec<EntityId> {
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn in_rect_across_chunks() {
+        let mut ecs = ECSWorld::new();
+        let mut index = SpatialIndex::new();
+        let origin = ChunkPos::new(0, 0);
+
+        // on both sides of the corner between four chunks
+        let a = ecs.spawn(());
+        let b = ecs.spawn(());
+        let c = ecs.spawn(());
+        let far = ecs.spawn(());
+        index.insert(a, &Position::free_in(ChunkPos::new(-1, -1), 15.5, 15.5));
+        index.insert(b, &Position::free_in(ChunkPos::new(0, 0), 0.5, 0.5));
+        index.insert(c, &Position::tile(ChunkPos::new(-1, 0), 15, 0));
+        index.insert(far, &Position::free_in(ChunkPos::new(1, 1), 0.5, 0.5));
+
+        assert_eq!(sorted(index.in_rect(&origin, -1.0, -1.0, 1.0, 1.0)), sorted(vec![a, b, c]));
+        assert_eq!(index.in_rect(&ChunkPos::new(1, 1), -0.5, -0.5, 1.0, 1.0), vec![far]);
+        assert!(index.in_rect(&origin, 2.0, 2.0, 3.0, 3.0).is_empty());
+    }
+
+    #[test]
+    fn update_moves_between_cells_and_chunks() {
+        let mut ecs = ECSWorld::new();
+        let mut index = SpatialIndex::new();
+        let origin = ChunkPos::new(0, 0);
+
+        let id = ecs.spawn(());
+        index.insert(id, &Position::free_in(origin.clone(), 1.0, 1.0));
+
+        index.update(id, &Position::free_in(origin.clone(), 9.0, 1.0));
+        assert!(index.in_rect(&origin, 0.0, 0.0, 2.0, 2.0).is_empty());
+        assert_eq!(index.in_rect(&origin, 8.0, 0.0, 10.0, 2.0), vec![id]);
+
+        index.update(id, &Position::free_in(origin.clone(), 17.0, 1.0));
+        assert!(index.in_chunk(&origin).is_empty());
+        assert_eq!(index.in_chunk(&ChunkPos::new(1, 0)), &[id]);
+        assert_eq!(index.in_rect(&origin, 16.5, 0.0, 17.5, 2.0), vec![id]);
+    }
+
+    #[test]
+    fn removed_entities_are_forgotten() {
+        let mut ecs = ECSWorld::new();
+        let mut index = SpatialIndex::new();
+        let origin = ChunkPos::new(0, 0);
+
+        let removed = ecs.spawn(());
+        let despawned = ecs.spawn((Position::free_in(origin.clone(), 2.0, 2.0),));
+        let moved = ecs.spawn((Position::free_in(origin.clone(), 12.0, 2.0),));
+        index.insert(removed, &Position::free_in(origin.clone(), 1.0, 1.0));
+        index.insert(despawned, &Position::free_in(origin.clone(), 2.0, 2.0));
+        index.insert(moved, &Position::free_in(origin.clone(), 3.0, 3.0));
+
+        index.remove(removed);
+        ecs.despawn(despawned).unwrap();
+        index.refresh(despawned, &ecs);
+        index.refresh(moved, &ecs);
+
+        assert!(index.in_rect(&origin, 0.0, 0.0, 4.0, 4.0).is_empty());
+        assert_eq!(index.in_chunk(&origin), &[moved]);
+        assert_eq!(index.in_rect(&origin, 11.0, 1.0, 13.0, 3.0), vec![moved]);
+    }
+}
@@ -8,6 +8,8 @@ use sdl2::video::WindowContext;
 use sdl2::pixels::PixelFormatEnum;
 use sdl2::rect;
 
+use serde::{Serialize, Deserialize};
+
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::BufReader;
@@ -21,6 +23,7 @@ macro_rules! setter {
     };
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 enum Direction {
     Twelve,
     Three,
@@ -39,6 +42,7 @@ impl Direction {
     }
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct TextureTransform{
     direction: Direction,
     scale: f32,
@@ -52,9 +56,13 @@ impl TextureTransform {
         }
     }
 
-    pub fn random_direction(mut self) -> Self {
+    pub fn random_direction(self) -> Self {
+        self.rotated(random::int(0..=3))
+    }
+
+    pub fn rotated(mut self, quarter_turns: u8) -> Self {
         self.direction =
-            match random::int(0..=3) {
+            match quarter_turns % 4 {
                 0 => Direction::Twelve,
                 1 => Direction::Three,
                 2 => Direction::Six,
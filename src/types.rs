@@ -6,3 +6,7 @@ pub type SizeType = u16;
 pub type ChunkPosType = i32;
 pub type GameObjectTypeId = u8;
 pub type UpdateFnIdType = GameObjectTypeId;
+
+// Used for keys in serialized structs. Serde would try to borrow a field
+// spelled `&'static str` from the input, which an alias prevents.
+pub type Key = &'static str;
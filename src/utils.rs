@@ -4,4 +4,10 @@ pub use counter::*;
 mod keyedslice;
 pub use keyedslice::*;
 
+mod intern;
+pub use intern::*;
+
+mod json;
+pub use json::*;
+
 pub mod macros;
@@ -0,0 +1,41 @@
+// Keys are `&'static str` everywhere, but keys read from files are owned
+// strings. Each distinct string is leaked once and reused afterwards.
+
+use serde::{Deserialize, Deserializer};
+
+use std::collections::HashSet;
+use std::sync::{Mutex, OnceLock};
+
+static STRINGS: OnceLock<Mutex<HashSet<&'static str>>> = OnceLock::new();
+
+pub fn intern(s: &str) -> &'static str {
+    let mut strings = STRINGS
+        .get_or_init(|| Mutex::new(HashSet::new()))
+        .lock()
+        .unwrap();
+
+    if let Some(interned) = strings.get(s) {
+        return interned
+    }
+
+    let interned: &'static str = Box::leak(s.into());
+    strings.insert(interned);
+    interned
+}
+
+// For use with `#[serde(deserialize_with = "...")]`
+pub fn deserialize_interned
+        <'de, D: Deserializer<'de>>(deserializer: D)
+        -> Result<&'static str, D::Error> {
+
+    let s = String::deserialize(deserializer)?;
+    Ok(intern(&s))
+}
+
+pub fn deserialize_interned_option
+        <'de, D: Deserializer<'de>>(deserializer: D)
+        -> Result<Option<&'static str>, D::Error> {
+
+    let s = Option::<String>::deserialize(deserializer)?;
+    Ok(s.map(|s| intern(&s)))
+}
@@ -0,0 +1,36 @@
+// Assets like structures and recipes are a folder of json files, one per
+// definition, with the file name as the key.
+
+use crate::prelude::*;
+use super::intern;
+
+use serde::de::DeserializeOwned;
+
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::Path;
+
+// Reads every json file in a folder, sorted by key so that the order doesn't
+// depend on the file system. `what` names the definitions in errors.
+pub fn load_json_dir<T: DeserializeOwned>(dir: &Path, what: &str) -> Result<Vec<(Key, T)>> {
+    let mut loaded = Vec::new();
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+            continue
+        }
+
+        let key = path.file_stem().and_then(|stem| stem.to_str())
+            .ok_or_else(|| anyhow!("invalid {} file name {:?}", what, path))?;
+
+        let reader = BufReader::new(File::open(&path)?);
+        let value: T = serde_json::from_reader(reader)
+            .with_context(|| format!("reading {} '{}'", what, key))?;
+        loaded.push((intern(key), value));
+    }
+
+    loaded.sort_by_key(|(key, _)| *key);
+
+    Ok(loaded)
+}
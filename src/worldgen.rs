@@ -0,0 +1,14 @@
+mod noise;
+pub use noise::hash;
+
+mod biome;
+pub use biome::*;
+
+mod terrain;
+pub use terrain::*;
+
+mod structure;
+pub use structure::*;
+
+mod workers;
+pub use workers::*;
@@ -0,0 +1,55 @@
+// Biomes, defined by the json files in the biomes folder. The file name is
+// the key of the biome.
+//
+// Biomes are picked by finding the one whose climate is closest to the
+// temperature and moisture at a given position.
+
+use crate::prelude::*;
+use crate::utils::{load_json_dir, deserialize_interned};
+
+use serde::Deserialize;
+
+use std::path::Path;
+
+#[derive(Deserialize)]
+pub struct Feature {
+    #[serde(deserialize_with = "deserialize_interned")]
+    pub key: Key,
+    pub density: f64, // chance per ground tile, before the clustering noise
+}
+
+#[derive(Deserialize)]
+pub struct Biome {
+    #[serde(skip)]
+    pub key: Key,
+    pub temperature: f64,
+    pub moisture: f64,
+    #[serde(deserialize_with = "deserialize_interned")]
+    pub ground: Key,
+    #[serde(deserialize_with = "deserialize_interned")]
+    pub patches: Key,
+    #[serde(default)]
+    pub features: Vec<Feature>,
+}
+
+impl Biome {
+    fn distance(&self, temperature: f64, moisture: f64) -> f64 {
+        (self.temperature - temperature).powi(2) + (self.moisture - moisture).powi(2)
+    }
+}
+
+// Loads every biome in a folder, sorted by key so that ties between climates
+// are always broken the same way.
+pub fn load_biomes(dir: &Path) -> Result<Vec<Biome>> {
+    Ok(load_json_dir(dir, "biome")?.into_iter()
+        .map(|(key, biome)| Biome { key, ..biome })
+        .collect())
+}
+
+pub fn closest_biome(biomes: &[Biome], temperature: f64, moisture: f64) -> &Biome {
+    biomes.iter()
+        .min_by(|a, b|
+            a.distance(temperature, moisture)
+                .total_cmp(&b.distance(temperature, moisture)))
+        .expect("no biomes defined")
+}
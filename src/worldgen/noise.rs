@@ -0,0 +1,74 @@
+// Deterministic noise functions. Everything here only depends on the seed and
+// the coordinates, so the same input always gives the same output.
+
+pub fn hash(seed: u64, x: i64, y: i64) -> u64 {
+    // splitmix64 finalizer over the combined input
+    let mut h = seed
+        ^ (x as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+        ^ (y as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f);
+    h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    h ^ (h >> 31)
+}
+
+// Like `hash`, but mapped to `0.0..1.0`.
+pub fn hash_f(seed: u64, x: i64, y: i64) -> f64 {
+    (hash(seed, x, y) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+fn smooth(t: f64) -> f64 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+
+fn value(seed: u64, x: f64, y: f64) -> f64 {
+    let x0 = x.floor();
+    let y0 = y.floor();
+    let tx = smooth(x - x0);
+    let ty = smooth(y - y0);
+    let (x0, y0) = (x0 as i64, y0 as i64);
+
+    lerp(
+        lerp(hash_f(seed, x0, y0),     hash_f(seed, x0 + 1, y0),     tx),
+        lerp(hash_f(seed, x0, y0 + 1), hash_f(seed, x0 + 1, y0 + 1), tx),
+        ty,
+    )
+}
+
+// Fractal value noise in the range `0.0..1.0`.
+pub struct Noise {
+    seed: u64,
+    scale: f64,
+    octaves: u8,
+}
+
+impl Noise {
+    // `salt` separates noise layers created from the same world seed.
+    pub fn new(seed: u64, salt: u64, scale: f64, octaves: u8) -> Self {
+        Self {
+            seed: hash(seed, salt as i64, 0),
+            scale,
+            octaves,
+        }
+    }
+
+    pub fn get(&self, x: f64, y: f64) -> f64 {
+        let mut sum = 0.0;
+        let mut total = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0 / self.scale;
+
+        for octave in 0..self.octaves {
+            let seed = self.seed.wrapping_add(octave as u64);
+            sum += value(seed, x * frequency, y * frequency) * amplitude;
+            total += amplitude;
+            amplitude *= 0.5;
+            frequency *= 2.0;
+        }
+
+        sum / total
+    }
+}
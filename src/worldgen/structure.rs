@@ -0,0 +1,47 @@
+// Multi-tile structures, defined by the json files in the structures folder.
+// The file name is the key of the structure.
+
+use crate::prelude::*;
+use crate::utils::{load_json_dir, deserialize_interned_option};
+use crate::components::InventoryItem;
+
+use serde::Deserialize;
+
+use std::path::Path;
+
+#[derive(Deserialize)]
+pub struct Piece {
+    pub x: i64, // offset from the origin of the structure
+    pub y: i64,
+    #[serde(default, deserialize_with = "deserialize_interned_option")]
+    pub ground: Option<Key>, // replaces the ground tile
+    #[serde(default, deserialize_with = "deserialize_interned_option")]
+    pub object: Option<Key>, // replaces whatever object was generated there
+    #[serde(default)]
+    pub items: Vec<InventoryItem>, // given to the object, for containers
+}
+
+#[derive(Deserialize)]
+pub struct Structure {
+    #[serde(skip)]
+    pub key: Key,
+    pub chance: f64, // chance of being placed in a structure cell
+    #[serde(default)]
+    pub biomes: Vec<String>, // biomes the origin can be in, any if empty
+    pub pieces: Vec<Piece>,
+}
+
+impl Structure {
+    // How far the pieces reach from the origin.
+    pub fn reach(&self) -> i64 {
+        self.pieces.iter().map(|piece| piece.x.abs().max(piece.y.abs())).max().unwrap_or(0)
+    }
+}
+
+// Loads every structure in a folder, sorted by key so that placement only
+// depends on the seed.
+pub fn load_structures(dir: &Path) -> Result<Vec<Structure>> {
+    Ok(load_json_dir(dir, "structure")?.into_iter()
+        .map(|(key, structure)| Structure { key, ..structure })
+        .collect())
+}
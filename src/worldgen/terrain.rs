@@ -0,0 +1,248 @@
+use crate::components::{ChunkPos, InventoryItem};
+use crate::constants::CHUNK_SIZE;
+use super::noise::*;
+use super::biome::*;
+use super::structure::*;
+
+use std::sync::Arc;
+
+const SALT_HEIGHT:      u64 = 1;
+const SALT_PATCHES:     u64 = 2;
+const SALT_FOREST:      u64 = 3;
+const SALT_FEATURE:     u64 = 4;
+const SALT_TEMPERATURE: u64 = 5;
+const SALT_MOISTURE:    u64 = 6;
+const SALT_ROTATION:    u64 = 7;
+const SALT_STRUCTURE:   u64 = 8;
+
+// Every structure cell holds at most one structure, whose origin lies in it.
+const STRUCTURE_CELL: i64 = 64;
+
+pub struct GeneratedObject {
+    pub col: u8,
+    pub row: u8,
+    pub key: &'static str,
+    pub items: Vec<InventoryItem>,
+}
+
+pub struct GeneratedChunk {
+    pub pos: ChunkPos,
+    pub tiles: Vec<(&'static str, u8)>, // (key, state), indexed by row * CHUNK_SIZE + col
+    pub objects: Vec<GeneratedObject>,
+}
+
+pub struct WorldGen {
+    seed: u64,
+    height: Noise,
+    patches: Noise,
+    forest: Noise,
+    temperature: Noise,
+    moisture: Noise,
+    biomes: Arc<[Biome]>,
+    structures: Arc<[Structure]>,
+    structure_reach: i64,
+}
+
+impl WorldGen {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            height:      Noise::new(seed, SALT_HEIGHT,      48.0,  4),
+            patches:     Noise::new(seed, SALT_PATCHES,     12.0,  2),
+            forest:      Noise::new(seed, SALT_FOREST,      32.0,  2),
+            temperature: Noise::new(seed, SALT_TEMPERATURE, 160.0, 2),
+            moisture:    Noise::new(seed, SALT_MOISTURE,    160.0, 2),
+            biomes: Arc::new([]),
+            structures: Arc::new([]),
+            structure_reach: 0,
+        }
+    }
+
+    pub fn with_biomes(mut self, biomes: Arc<[Biome]>) -> Self {
+        self.biomes = biomes;
+        self
+    }
+
+    pub fn with_structures(mut self, structures: Arc<[Structure]>) -> Self {
+        self.structure_reach = structures.iter().map(|s| s.reach()).max().unwrap_or(0);
+        self.structures = structures;
+        self
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn biome_at(&self, x: i64, y: i64) -> &Biome {
+        let (fx, fy) = (x as f64, y as f64);
+        closest_biome(&self.biomes, self.temperature.get(fx, fy), self.moisture.get(fx, fy))
+    }
+
+    fn ground_at(&self, x: i64, y: i64, biome: &Biome) -> &'static str {
+        let (fx, fy) = (x as f64, y as f64);
+        let height = self.height.get(fx, fy);
+
+        if height < 0.3 {
+            "water"
+        } else if height < 0.32 {
+            "sand"
+        } else if height > 0.64 {
+            "stone"
+        } else if self.patches.get(fx, fy) > 0.68 {
+            biome.patches
+        } else {
+            biome.ground
+        }
+    }
+
+    fn feature_at(&self, x: i64, y: i64, biome: &Biome) -> Option<&'static str> {
+        let clustering = self.forest.get(x as f64, y as f64) * 2.0;
+
+        biome.features.iter().enumerate().find(|(i, feature)| {
+            let seed = hash(self.seed, SALT_FEATURE as i64, *i as i64);
+            hash_f(seed, x, y) < feature.density * clustering
+        }).map(|(_, feature)| feature.key)
+    }
+
+    pub fn generate(&self, pos: &ChunkPos) -> GeneratedChunk {
+        let mut tiles = Vec::with_capacity(CHUNK_SIZE * CHUNK_SIZE);
+        let mut objects = Vec::new();
+
+        for row in 0..CHUNK_SIZE {
+            for col in 0..CHUNK_SIZE {
+                let x = pos.x as i64 * CHUNK_SIZE as i64 + col as i64;
+                let y = pos.y as i64 * CHUNK_SIZE as i64 + row as i64;
+                let biome = self.biome_at(x, y);
+                let tile = self.ground_at(x, y, biome);
+
+                if tile == biome.ground
+                        && let Some(key) = self.feature_at(x, y, biome) {
+                    objects.push(GeneratedObject {
+                        col: col as u8,
+                        row: row as u8,
+                        key,
+                        items: Vec::new(),
+                    });
+                }
+
+                let rotation = hash(self.seed, SALT_ROTATION as i64, 0);
+                tiles.push((tile, (hash(rotation, x, y) % 4) as u8));
+            }
+        }
+
+        let mut chunk = GeneratedChunk {
+            pos: pos.clone(),
+            tiles,
+            objects,
+        };
+        self.place_structures(&mut chunk);
+        chunk
+    }
+
+    // The structure whose origin lies in a structure cell, if any.
+    fn structure_in(&self, cx: i64, cy: i64) -> Option<(&Structure, i64, i64)> {
+        let (index, structure) = self.structures.iter().enumerate().find(|(i, structure)| {
+            let seed = hash(self.seed, SALT_STRUCTURE as i64, *i as i64);
+            hash_f(seed, cx, cy) < structure.chance
+        })?;
+
+        let seed = hash(self.seed, SALT_STRUCTURE as i64, -1 - index as i64);
+        let x = cx * STRUCTURE_CELL + (hash(seed, cx, cy) % STRUCTURE_CELL as u64) as i64;
+        let y = cy * STRUCTURE_CELL + (hash(seed, cy, cx) % STRUCTURE_CELL as u64) as i64;
+
+        let biome = self.biome_at(x, y);
+        if !structure.biomes.is_empty() && !structure.biomes.iter().any(|key| key == biome.key) {
+            return None
+        }
+        if self.ground_at(x, y, biome) == "water" {
+            return None
+        }
+
+        Some((structure, x, y))
+    }
+
+    // Places the pieces of every structure that reaches into the chunk. The
+    // structures only depend on the seed, so the pieces in neighbouring
+    // chunks match up no matter which chunk is generated first.
+    fn place_structures(&self, chunk: &mut GeneratedChunk) {
+        if self.structures.is_empty() {
+            return
+        }
+
+        let size = CHUNK_SIZE as i64;
+        let (x1, y1) = (chunk.pos.x as i64 * size, chunk.pos.y as i64 * size);
+        let (x2, y2) = (x1 + size - 1, y1 + size - 1);
+        let reach = self.structure_reach;
+
+        for cx in (x1 - reach).div_euclid(STRUCTURE_CELL)..=(x2 + reach).div_euclid(STRUCTURE_CELL) {
+            for cy in (y1 - reach).div_euclid(STRUCTURE_CELL)..=(y2 + reach).div_euclid(STRUCTURE_CELL) {
+                let Some((structure, x, y)) = self.structure_in(cx, cy) else { continue };
+
+                for piece in &structure.pieces {
+                    let (x, y) = (x + piece.x, y + piece.y);
+                    if x < x1 || x > x2 || y < y1 || y > y2 {
+                        continue
+                    }
+
+                    let (col, row) = ((x - x1) as u8, (y - y1) as u8);
+
+                    // pieces clear away whatever was generated on their tile
+                    chunk.objects.retain(|object| object.col != col || object.row != row);
+
+                    if let Some(ground) = piece.ground {
+                        chunk.tiles[row as usize * CHUNK_SIZE + col as usize].0 = ground;
+                    }
+
+                    if let Some(key) = piece.object {
+                        chunk.objects.push(GeneratedObject {
+                            col,
+                            row,
+                            key,
+                            items: piece.items.clone(),
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn worldgen(seed: u64) -> WorldGen {
+        let biomes = load_biomes(Path::new("assets/default/biomes")).unwrap();
+        let structures = load_structures(Path::new("assets/default/structures")).unwrap();
+        WorldGen::new(seed)
+            .with_biomes(biomes.into())
+            .with_structures(structures.into())
+    }
+
+    // (col, row, key, items) of every object
+    type Objects = Vec<(u8, u8, &'static str, usize)>;
+
+    fn summary(chunk: &GeneratedChunk) -> (Vec<(&'static str, u8)>, Objects) {
+        let objects = chunk.objects.iter()
+            .map(|object| (object.col, object.row, object.key, object.items.len()))
+            .collect();
+        (chunk.tiles.clone(), objects)
+    }
+
+    #[test]
+    fn same_seed_same_chunks() {
+        let (a, b) = (worldgen(42), worldgen(42));
+
+        for (x, y) in [(0, 0), (-3, 7), (12, -5)] {
+            let pos = ChunkPos::new(x, y);
+            assert_eq!(summary(&a.generate(&pos)), summary(&b.generate(&pos)));
+        }
+    }
+
+    #[test]
+    fn different_seed_different_chunks() {
+        let pos = ChunkPos::new(0, 0);
+        assert_ne!(summary(&worldgen(1).generate(&pos)), summary(&worldgen(2).generate(&pos)));
+    }
+}
@@ -0,0 +1,168 @@
+// Generates chunks on a pool of worker threads, so the main thread only has
+// to merge the finished chunks into the world.
+
+use super::{WorldGen, GeneratedChunk};
+use crate::prelude::*;
+use crate::components::ChunkPos;
+
+use std::collections::HashSet;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, Sender, Receiver, TryRecvError};
+use std::thread;
+
+// A generated chunk, or the position of one whose generation panicked.
+type JobResult = std::result::Result<GeneratedChunk, ChunkPos>;
+
+pub struct ChunkGenerator {
+    worldgen: Arc<WorldGen>,
+    jobs: Sender<ChunkPos>,
+    results: Receiver<JobResult>,
+    pending: HashSet<ChunkPos>,
+}
+
+fn work(
+        worldgen: Arc<WorldGen>,
+        jobs: Arc<Mutex<Receiver<ChunkPos>>>,
+        results: Sender<JobResult>) {
+
+    loop {
+        // the lock is only held while waiting for a job, not while generating
+        let job = match jobs.lock() {
+            Ok(jobs) => jobs.recv(),
+            Err(_) => return,
+        };
+
+        // the generator was dropped
+        let Ok(pos) = job else { return };
+
+        // a panic is reported instead of leaving the chunk pending forever
+        let result = panic::catch_unwind(AssertUnwindSafe(|| worldgen.generate(&pos)))
+            .map_err(|_| pos);
+
+        if results.send(result).is_err() {
+            return
+        }
+    }
+}
+
+impl ChunkGenerator {
+    pub fn new(worldgen: WorldGen, threads: usize) -> Self {
+        let worldgen = Arc::new(worldgen);
+        let (jobs, job_receiver) = mpsc::channel();
+        let (result_sender, results) = mpsc::channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+
+        for i in 0..threads {
+            let worldgen = worldgen.clone();
+            let job_receiver = job_receiver.clone();
+            let result_sender = result_sender.clone();
+
+            thread::Builder::new()
+                .name(format!("worldgen {}", i))
+                .spawn(move || work(worldgen, job_receiver, result_sender))
+                .expect("couldn't spawn worldgen thread");
+        }
+
+        Self {
+            worldgen,
+            jobs,
+            results,
+            pending: HashSet::new(),
+        }
+    }
+
+    pub fn worldgen(&self) -> &WorldGen {
+        &self.worldgen
+    }
+
+    // Queues a chunk for generation, unless it already is.
+    pub fn request(&mut self, pos: ChunkPos) {
+        if self.pending.insert(pos.clone()) {
+            // the workers only stop once the sender is dropped
+            let _ = self.jobs.send(pos);
+        }
+    }
+
+    // Chunks that were finished since the last call. Fails if a worker
+    // panicked, or if there are no workers left to finish pending chunks.
+    pub fn finished(&mut self) -> Result<Vec<GeneratedChunk>> {
+        let mut chunks = Vec::new();
+
+        loop {
+            match self.results.try_recv() {
+                Ok(Ok(chunk)) => {
+                    self.pending.remove(&chunk.pos);
+                    chunks.push(chunk);
+                },
+                Ok(Err(pos)) => bail!("worldgen panicked generating chunk {:?}", pos),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    if !self.pending.is_empty() {
+                        bail!("no worldgen threads left for {} pending chunks",
+                            self.pending.len());
+                    }
+                    break
+                },
+            }
+        }
+
+        Ok(chunks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::worldgen::{load_biomes, load_structures};
+    use std::path::Path;
+    use std::time::{Duration, Instant};
+
+    // Waits for `count` chunks, or the first error.
+    fn wait_for(generator: &mut ChunkGenerator, count: usize) -> Result<Vec<GeneratedChunk>> {
+        let start = Instant::now();
+        let mut chunks = Vec::new();
+        while chunks.len() < count {
+            assert!(start.elapsed() < Duration::from_secs(10), "timed out waiting for worldgen");
+            chunks.extend(generator.finished()?);
+            thread::sleep(Duration::from_millis(1));
+        }
+        Ok(chunks)
+    }
+
+    #[test]
+    fn generates_requested_chunks() {
+        let biomes = load_biomes(Path::new("assets/default/biomes")).unwrap();
+        let structures = load_structures(Path::new("assets/default/structures")).unwrap();
+        let worldgen = WorldGen::new(7)
+            .with_biomes(biomes.into())
+            .with_structures(structures.into());
+        let mut generator = ChunkGenerator::new(worldgen, 2);
+
+        generator.request(ChunkPos::new(0, 0));
+        generator.request(ChunkPos::new(-1, 2));
+        generator.request(ChunkPos::new(0, 0));
+
+        let chunks = wait_for(&mut generator, 2).unwrap();
+        assert!(chunks.iter().any(|chunk| chunk.pos == ChunkPos::new(-1, 2)));
+        assert!(generator.pending.is_empty());
+    }
+
+    #[test]
+    fn panicking_worker_fails() {
+        // without biomes, generating a chunk panics
+        let mut generator = ChunkGenerator::new(WorldGen::new(7), 1);
+        generator.request(ChunkPos::new(3, 4));
+
+        assert!(wait_for(&mut generator, 1).is_err());
+    }
+
+    #[test]
+    fn pending_without_workers_fails() {
+        let mut generator = ChunkGenerator::new(WorldGen::new(7), 0);
+        assert!(generator.finished().unwrap().is_empty());
+
+        generator.request(ChunkPos::new(0, 0));
+        assert!(generator.finished().is_err());
+    }
+}